@@ -0,0 +1,198 @@
+use crate::{
+	client::{check_elapsed, parse_retry_after, status_error, ClientBuilder, Error, Result},
+	decode::{decode_reader, decode_rows, PageInfo},
+	range::RangeCheck,
+	ratelimit::RateLimiter,
+	reference::reference_data::ReferenceCache,
+	time::TimeConversion
+};
+use backoff::{backoff::Backoff, ExponentialBackoff};
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use serde::de::DeserializeOwned;
+use std::{io::Read, time::Instant};
+
+// Same endpoints as `Client` but over reqwest. Response types and post-processing are shared
+// with the blocking client so callers can switch between the two.
+#[derive(Clone)]
+pub struct AsyncClient {
+	pub client: reqwest::Client,
+	pub api_uri: String,
+	pub stream_uri: String,
+	pub key: String,
+	pub backoff: ExponentialBackoff,
+	pub ratelimit: Option<RateLimiter>,
+	pub time_conversion: TimeConversion,
+	pub range_check: RangeCheck,
+	pub(crate) reference: ReferenceCache
+}
+
+impl AsyncClient {
+	pub fn new() -> Result<AsyncClient> { ClientBuilder::from_env().build_async() }
+
+	// Clones share the limiter, so set this before cloning
+	pub fn with_rate_limit(mut self, requests_per_minute: u32, burst: u32) -> Self {
+		self.ratelimit = Some(RateLimiter::new(requests_per_minute, burst));
+		self
+	}
+
+	// Sends a request, checks its status and downloads the body
+	async fn send(
+		&self,
+		uri: &str,
+		start: Instant
+	) -> std::result::Result<(Bytes, bool), backoff::Error<Error>> {
+		if let Some(ratelimit) = &self.ratelimit {
+			ratelimit.wait_async().await;
+		}
+		let resp = self
+			.client
+			.get(uri)
+			.header("accept-encoding", "gzip")
+			.header("authorization", format!("Bearer {}", self.key))
+			.send()
+			.await
+			.map_err(|e| backoff::Error::transient(Error::AsyncRequestError(e)))?;
+
+		let is_gzip = resp
+			.headers()
+			.get("content-encoding")
+			.map(|v| v == "gzip")
+			.unwrap_or(false);
+		let status = resp.status().as_u16();
+		if status != 200 {
+			let retry_after = resp
+				.headers()
+				.get("retry-after")
+				.and_then(|v| v.to_str().ok())
+				.and_then(parse_retry_after);
+			let body = resp.bytes().await.unwrap_or_default();
+			let err = status_error(status, uri, &body, is_gzip, retry_after);
+			return Err(check_elapsed(err, start, self.backoff.max_elapsed_time));
+		}
+
+		let bytes = resp
+			.bytes()
+			.await
+			.map_err(|e| backoff::Error::transient(Error::AsyncRequestError(e)))?;
+
+		Ok((bytes, is_gzip))
+	}
+
+	// Bodies are already in memory so only the download is retried
+	async fn send_with_retry(&self, uri: &str) -> Result<(Bytes, bool)> {
+		let start = Instant::now();
+		let mut backoff = self.backoff.clone();
+		backoff.reset();
+		backoff::future::retry(backoff, || self.send(uri, start)).await
+	}
+
+	pub async fn get_response<T: DeserializeOwned>(&self, uri: &str) -> Result<T> {
+		let (bytes, is_gzip) = self.send_with_retry(uri).await?;
+		decode_reader(uri, body_reader(&bytes, is_gzip)).map_err(into_err)
+	}
+
+	// Like `get_response` but passes each of `results` to `on_row` instead of collecting them. The
+	// body is downloaded in full first, so a whole page is in memory at once.
+	pub async fn get_rows<T: DeserializeOwned>(
+		&self,
+		uri: &str,
+		mut on_row: impl FnMut(T)
+	) -> Result<PageInfo> {
+		let (bytes, is_gzip) = self.send_with_retry(uri).await?;
+		decode_rows(uri, body_reader(&bytes, is_gzip), &mut on_row).map_err(into_err)
+	}
+}
+
+fn body_reader(bytes: &[u8], is_gzip: bool) -> Box<dyn Read + '_> {
+	if is_gzip {
+		Box::new(GzDecoder::new(bytes))
+	} else {
+		Box::new(bytes)
+	}
+}
+
+fn into_err(e: backoff::Error<Error>) -> Error {
+	match e {
+		backoff::Error::Transient { err, .. } => err,
+		backoff::Error::Permanent(err) => err
+	}
+}
+
+#[cfg(test)]
+mod async_client {
+	use crate::{
+		core::aggs::Timespan,
+		test_server::{StubResponse, StubServer}
+	};
+	use chrono::NaiveDate;
+	use flate2::{write::GzEncoder, Compression};
+	use std::io::Write;
+
+	const AGGS: &str = r#"{"ticker":"AAPL","queryCount":2,"resultsCount":2,"adjusted":true,
+		"results":[{"v":100,"vw":119.5,"o":119.6,"c":119.5,"h":119.6,"l":119.4,"t":1604566800000,"n":3},
+		{"v":200,"vw":119.6,"o":119.5,"c":119.7,"h":119.7,"l":119.5,"t":1604566860000,"n":5}],
+		"status":"OK","request_id":"x","count":2}"#;
+
+	#[tokio::test]
+	async fn matches_blocking() {
+		let server = StubServer::new(vec![StubResponse::new(200, AGGS)]);
+		let date = NaiveDate::from_ymd_opt(2020, 11, 5).unwrap();
+		let resp = server
+			.client()
+			.get_aggs("AAPL", 1, Timespan::Minute, date, date, None)
+			.unwrap();
+		let async_resp = server
+			.async_client()
+			.get_aggs("AAPL", 1, Timespan::Minute, date, date, None)
+			.await
+			.unwrap();
+		let request_line = |i: usize| server.request(i).lines().next().unwrap().to_string();
+		assert_eq!(request_line(0), request_line(1));
+		assert_eq!(resp.results.len(), async_resp.results.len());
+		assert_eq!(resp.results[0].ts, async_resp.results[0].ts);
+		assert_eq!(
+			async_resp.results[0].ts.to_string(),
+			"2020-11-05 04:00:00.000000000 EST"
+		);
+		assert_eq!(async_resp.results[0].symbol, "AAPL");
+	}
+
+	#[tokio::test]
+	async fn get_all_trades() {
+		let trade = |id: u64, nanos: i64| {
+			format!(
+				r#"{{"conditions":[12],"exchange":11,"id":"{}","price":296.24,"sequence_number":{},
+				"sip_timestamp":{},"size":100,"tape":3}}"#,
+				id, id, nanos
+			)
+		};
+		let first = format!(
+			r#"{{"status":"OK","results":[{},{}],
+			"next_url":"https://api.polygon.io/v3/trades/AAPL?cursor=YQ%3D%3D"}}"#,
+			trade(1000000001, 1577955600000000000),
+			trade(1000000002, 1577955600000000001)
+		);
+		let second = format!(
+			r#"{{"status":"OK","results":[{}]}}"#,
+			trade(1000000003, 1578013200000000000)
+		);
+		let mut gzipped = StubResponse::new(200, "").header("Content-Encoding", "gzip");
+		let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+		encoder.write_all(second.as_bytes()).unwrap();
+		gzipped.body = encoder.finish().unwrap();
+		let server = StubServer::new(vec![StubResponse::new(200, &first), gzipped]);
+
+		let trades = server
+			.async_client()
+			.get_all_trades("AAPL", NaiveDate::from_ymd_opt(2020, 1, 2).unwrap())
+			.await
+			.unwrap();
+		assert!(server
+			.request(1)
+			.starts_with("GET /v3/trades/AAPL?cursor=YQ%3D%3D "));
+		assert_eq!(trades.len(), 3);
+		assert_eq!(trades[0].ticker, "AAPL");
+		assert_eq!(trades[2].sequence_number, Some(1000000003));
+	}
+}
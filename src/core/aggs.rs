@@ -2,15 +2,18 @@ extern crate serde_json;
 extern crate ureq;
 
 use super::Candle;
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
 use crate::{
 	client::{Client, Result},
-	helpers::make_params,
+	helpers::{encode_segment, make_params, Order, Query},
+	range::{Range, RangeFilter},
+	time::{QueryTime, TimeConversion},
 	with_param
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Timespan {
 	Minute,
 	Hour,
@@ -34,24 +37,85 @@ pub struct AggResponse {
 	pub results: Vec<Candle>,
 	// For debugging
 	pub request_id: String,
-	pub uri: Option<String>
+	pub uri: Option<String>,
+	// Rows dropped by a lenient `RangeCheck`
+	#[serde(skip)]
+	pub out_of_range: usize
 }
 
-pub struct AggsParams<'a> {
-	pub params: HashMap<&'a str, String>
+#[derive(Clone, Debug, Default)]
+pub struct AggsParams {
+	pub(crate) params: Query
 }
 
-impl<'a> AggsParams<'a> {
+impl AggsParams {
 	with_param!(unadjusted, bool);
 
-	with_param!(sort, &str);
+	with_param!(sort, Order);
 
 	with_param!(limit, i32);
 
-	pub fn new() -> Self {
-		Self {
-			params: HashMap::with_capacity(3)
-		}
+	pub fn new() -> Self { Self::default() }
+}
+
+impl AsRef<Query> for AggsParams {
+	fn as_ref(&self) -> &Query { &self.params }
+}
+
+// Longest a bar can be, since months and years vary
+fn bar_nanos(multiplier: i64, timespan: &Timespan) -> i64 {
+	let secs = match timespan {
+		Timespan::Minute => 60,
+		Timespan::Hour => 60 * 60,
+		Timespan::Day => 24 * 60 * 60,
+		Timespan::Week => 7 * 24 * 60 * 60,
+		Timespan::Month => 31 * 24 * 60 * 60,
+		Timespan::Quarter => 92 * 24 * 60 * 60,
+		Timespan::Year => 366 * 24 * 60 * 60
+	};
+	multiplier
+		.saturating_mul(secs)
+		.saturating_mul(1_000_000_000)
+}
+
+fn aggs_range(
+	symbol: &str,
+	multiplier: i64,
+	timespan: &Timespan,
+	from: &str,
+	to: &str
+) -> Option<Range> {
+	let zone = TimeConversion::Exchange.zone(symbol);
+	Range::for_aggs(from, to, bar_nanos(multiplier, timespan), zone)
+}
+
+fn aggs_uri(
+	api_uri: &str,
+	symbol: &str,
+	multiplier: i64,
+	timespan: Timespan,
+	from: &str,
+	to: &str,
+	params: Option<&AggsParams>
+) -> String {
+	format!(
+		"{}/v2/aggs/ticker/{}/range/{}/{}/{}/{}{}",
+		api_uri,
+		encode_segment(symbol),
+		multiplier,
+		format!("{:?}", timespan).to_lowercase(),
+		from,
+		to,
+		make_params(params),
+	)
+}
+
+fn fix_aggs(resp: &mut AggResponse, symbol: &str, conversion: TimeConversion) {
+	let zone = conversion.zone(symbol);
+	for candle in resp.results.iter_mut() {
+		candle.ts = candle.ts.with_zone(zone);
+		// Add symbol
+		candle.symbol = resp.symbol.clone();
 	}
 }
 
@@ -61,42 +125,56 @@ impl Client {
 		symbol: &str,
 		multiplier: i64,
 		timespan: Timespan,
-		from: &str,
-		to: &str,
-		params: Option<&HashMap<&str, String>>
+		from: impl QueryTime,
+		to: impl QueryTime,
+		params: Option<&AggsParams>
 	) -> Result<AggResponse> {
-		let uri = format!(
-			"{}/v2/aggs/ticker/{}/range/{}/{}/{}/{}{}",
-			self.api_uri,
+		let (from, to) = (&from.millis_param(), &to.millis_param());
+		let range = aggs_range(symbol, multiplier, &timespan, from, to);
+		let uri = aggs_uri(
+			&self.api_uri,
 			symbol,
 			multiplier,
-			format!("{:?}", timespan).to_lowercase(),
+			timespan,
 			from,
 			to,
-			make_params(params),
+			params
 		);
 		let mut resp = self.get_response::<AggResponse>(&uri)?;
+		fix_aggs(&mut resp, symbol, self.time_conversion);
+		resp.out_of_range =
+			RangeFilter::new(range, self.range_check).retain(&uri, &mut resp.results)?;
 
-		let is_equity = !symbol.contains(":");
-		let mut min_ts = i64::MAX;
-		let mut max_ts = i64::MIN;
-		for candle in resp.results.iter_mut() {
-			// Polygon returns GMT milliseconds for this endpoint
-			if is_equity {
-				// Subtract 5h of milliseconds
-				candle.ts -= 5 * 60 * 60 * 1_000;
-			}
-			// Convert to ns
-			candle.ts *= 1_000_000;
-			if candle.ts > max_ts {
-				max_ts = candle.ts;
-			}
-			if candle.ts < min_ts {
-				min_ts = candle.ts;
-			}
-			// Add symbol
-			candle.symbol = resp.symbol.clone();
-		}
+		Ok(resp)
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_aggs(
+		&self,
+		symbol: &str,
+		multiplier: i64,
+		timespan: Timespan,
+		from: impl QueryTime,
+		to: impl QueryTime,
+		params: Option<&AggsParams>
+	) -> Result<AggResponse> {
+		let (from, to) = (&from.millis_param(), &to.millis_param());
+		let range = aggs_range(symbol, multiplier, &timespan, from, to);
+		let uri = aggs_uri(
+			&self.api_uri,
+			symbol,
+			multiplier,
+			timespan,
+			from,
+			to,
+			params
+		);
+		let mut resp = self.get_response::<AggResponse>(&uri).await?;
+		fix_aggs(&mut resp, symbol, self.time_conversion);
+		resp.out_of_range =
+			RangeFilter::new(range, self.range_check).retain(&uri, &mut resp.results)?;
 
 		Ok(resp)
 	}
@@ -105,14 +183,27 @@ impl Client {
 #[cfg(test)]
 mod aggs {
 	use super::Timespan;
-	use crate::{client::Client, core::aggs::AggsParams};
+	use crate::{
+		client::{Client, ClientBuilder},
+		core::aggs::AggsParams,
+		range::RangeCheck,
+		test_server::{StubResponse, StubServer}
+	};
+	use chrono::NaiveDate;
 
 	#[test]
 	fn aapl() {
 		let client = Client::new().unwrap();
 		let sym = String::from("AAPL");
 		let resp = client
-			.get_aggs(&sym, 1, Timespan::Minute, "2020-11-05", "2020-11-05", None)
+			.get_aggs(
+				&sym,
+				1,
+				Timespan::Minute,
+				NaiveDate::from_ymd_opt(2020, 11, 5).unwrap(),
+				NaiveDate::from_ymd_opt(2020, 11, 5).unwrap(),
+				None
+			)
 			.unwrap();
 		assert_eq!(resp.results.len(), 941);
 		assert_eq!(resp.results.len(), resp.results_count);
@@ -122,18 +213,37 @@ mod aggs {
 
 	#[test]
 	fn mac() {
-		let client = Client::new().unwrap();
+		// Polygon has returned rows outside this range, which the default would silently drop
+		let client = ClientBuilder::from_env()
+			.range_check(RangeCheck::Strict)
+			.build()
+			.unwrap();
 		let sym = String::from("MAC");
-		let params = AggsParams::new().unadjusted(true).params;
+		let params = AggsParams::new().unadjusted(true);
 		client
 			.get_aggs(
 				&sym,
 				1,
 				Timespan::Minute,
-				"2004-01-01",
-				"2020-02-01",
+				NaiveDate::from_ymd_opt(2004, 1, 1).unwrap(),
+				NaiveDate::from_ymd_opt(2020, 2, 1).unwrap(),
 				Some(&params)
 			)
 			.unwrap();
 	}
+
+	#[test]
+	fn crypto_volume() {
+		let server = StubServer::new(vec![StubResponse::new(
+			200,
+			r#"{"ticker":"X:BTCUSD","queryCount":1,"resultsCount":1,"adjusted":true,"results":[{"v":951.6112,"vw":33050.1,"o":33045.2,"c":33052.9,"h":33060,"l":33040.1,"t":1610460000000,"n":412}],"request_id":"1"}"#
+		)]);
+		let date = NaiveDate::from_ymd_opt(2021, 1, 12).unwrap();
+		let resp = server
+			.client()
+			.get_aggs("X:BTCUSD", 1, Timespan::Minute, date, date, None)
+			.unwrap();
+		assert_eq!(resp.results[0].volume, 951.6112);
+		assert_eq!(resp.results[0].num_ticks, 412);
+	}
 }
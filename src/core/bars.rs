@@ -0,0 +1,472 @@
+// Builds `Candle`s from trades like Polygon's aggregates. Trades update each part of a bar
+// according to their conditions (see `equities::conditions`) and corrections are skipped. A bar
+// with only volume-eligible trades opens and closes at the previous bar's close.
+// Minute and hour bars are aligned to the Unix epoch and longer bars to calendar boundaries in
+// the symbol's exchange time zone, with weeks starting on Sunday.
+use super::{aggs::Timespan, Candle};
+use crate::{
+	equities::trades::Trade,
+	range::Range,
+	time::{TimeConversion, Timestamp}
+};
+use chrono::{Datelike, Duration, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+
+// Running totals for one bar
+struct Bar {
+	ts:        Timestamp,
+	symbol:    String,
+	// Earliest and latest eligible trades' times and prices
+	open:      Option<(Timestamp, f64)>,
+	close:     Option<(Timestamp, f64)>,
+	high:      f64,
+	low:       f64,
+	volume:    u64,
+	notional:  f64,
+	num_ticks: u64
+}
+
+impl Bar {
+	fn new(ts: Timestamp, symbol: &str) -> Self {
+		Self {
+			ts,
+			symbol: symbol.to_string(),
+			open: None,
+			close: None,
+			high: f64::MIN,
+			low: f64::MAX,
+			volume: 0,
+			notional: 0.0,
+			num_ticks: 0
+		}
+	}
+
+	fn add(&mut self, trade: &Trade) {
+		if trade.updates_open_close() {
+			if self.open.is_none_or(|(ts, _)| trade.time < ts) {
+				self.open = Some((trade.time, trade.price));
+			}
+			if self.close.is_none_or(|(ts, _)| trade.time >= ts) {
+				self.close = Some((trade.time, trade.price));
+			}
+		}
+		if trade.updates_high_low() {
+			self.high = self.high.max(trade.price);
+			self.low = self.low.min(trade.price);
+		}
+		if trade.updates_volume() {
+			self.volume += trade.size as u64;
+			self.notional += trade.price * trade.size as f64;
+			self.num_ticks += 1;
+		}
+	}
+
+	// Without a trade eligible to set the open and close, volume-only trades still make a bar at
+	// `prev_close`. `None` when there's neither.
+	fn finish(self, prev_close: Option<f64>) -> Option<Candle> {
+		let carried = prev_close.filter(|_| self.volume > 0);
+		let open = self.open.map(|(_, price)| price).or(carried)?;
+		let close = self.close.map(|(_, price)| price).or(carried)?;
+		Some(Candle {
+			ts: self.ts,
+			symbol: self.symbol,
+			open,
+			high: self.high.max(open).max(close),
+			low: self.low.min(open).min(close),
+			close,
+			volume: self.volume as f64,
+			vwap: match self.volume {
+				0 => f32::NAN,
+				v => (self.notional / v as f64) as f32
+			},
+			num_ticks: self.num_ticks
+		})
+	}
+}
+
+// Bars of `multiplier` `timespan`s. `push` trades in time order and collect the bars it returns
+// (then `finish`), or `build` a whole batch in any order.
+pub struct BarBuilder {
+	multiplier: i64,
+	timespan:   Timespan,
+	current:    Option<Bar>,
+	// Of the last bar returned
+	close:      Option<f64>,
+	// Trades pushed after their bar was returned
+	pub late:   usize
+}
+
+fn date_start(date: NaiveDate, zone: Tz) -> i64 {
+	Range::for_date(date, zone)
+		.map(|r| r.start)
+		.unwrap_or_default()
+}
+
+fn month_start(months: i32, zone: Tz) -> i64 {
+	NaiveDate::from_ymd_opt(months.div_euclid(12), months.rem_euclid(12) as u32 + 1, 1)
+		.map(|d| date_start(d, zone))
+		.unwrap_or_default()
+}
+
+impl BarBuilder {
+	pub fn new(multiplier: i64, timespan: Timespan) -> Self {
+		Self {
+			multiplier: multiplier.max(1),
+			timespan,
+			current: None,
+			close: None,
+			late: 0
+		}
+	}
+
+	// Start of the bar containing `nanos`, with calendar bars in `zone`
+	fn bar_start(&self, nanos: i64, zone: Tz) -> i64 {
+		let fixed = |secs: i64| {
+			let size = self.multiplier * secs * 1_000_000_000;
+			nanos.div_euclid(size) * size
+		};
+		let date = zone.timestamp_nanos(nanos).date_naive();
+		// Aligned in whole dates so bars start at local midnight on both sides of DST changes
+		let days = |since: NaiveDate, days_per_bar: i64| {
+			let since_days = (date - since).num_days();
+			let size = days_per_bar * self.multiplier;
+			let aligned = since + Duration::days(since_days.div_euclid(size) * size);
+			date_start(aligned, zone)
+		};
+		let months = |months_per_bar: i32| {
+			let size = months_per_bar * self.multiplier as i32;
+			let months = date.year() * 12 + date.month0() as i32;
+			month_start(months.div_euclid(size) * size, zone)
+		};
+		match self.timespan {
+			Timespan::Minute => fixed(60),
+			Timespan::Hour => fixed(60 * 60),
+			Timespan::Day => days(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(), 1),
+			// A Sunday
+			Timespan::Week => days(NaiveDate::from_ymd_opt(1970, 1, 4).unwrap(), 7),
+			Timespan::Month => months(1),
+			Timespan::Quarter => months(3),
+			Timespan::Year => months(12)
+		}
+	}
+
+	fn start(&self, trade: &Trade) -> Timestamp {
+		let zone = TimeConversion::Exchange.zone(&trade.ticker);
+		let start = self.bar_start(trade.time.nanos(), zone);
+		Timestamp::from_nanos(start, trade.time.zone())
+	}
+
+	// Returns the previous bar once a trade starts a new one
+	pub fn push(&mut self, trade: &Trade) -> Option<Candle> {
+		if trade.correction != 0 {
+			return None;
+		}
+		let start = self.start(trade);
+		let mut done = None;
+		match &self.current {
+			Some(bar) if start < bar.ts => {
+				self.late += 1;
+				return None;
+			}
+			Some(bar) if start > bar.ts => {
+				done = self.current.take().and_then(|bar| self.finish_bar(bar))
+			}
+			_ => {}
+		}
+		self
+			.current
+			.get_or_insert_with(|| Bar::new(start, &trade.ticker))
+			.add(trade);
+
+		done
+	}
+
+	// The bar in progress
+	pub fn finish(&mut self) -> Option<Candle> {
+		self.current.take().and_then(|bar| self.finish_bar(bar))
+	}
+
+	fn finish_bar(&mut self, bar: Bar) -> Option<Candle> {
+		let candle = bar.finish(self.close)?;
+		self.close = Some(candle.close);
+		Some(candle)
+	}
+
+	// Bars in time order. Trades needn't be sorted.
+	pub fn build<'a>(&self, trades: impl IntoIterator<Item = &'a Trade>) -> Vec<Candle> {
+		let mut bars = BTreeMap::<Timestamp, Bar>::new();
+		for trade in trades.into_iter().filter(|t| t.correction == 0) {
+			let start = self.start(trade);
+			bars
+				.entry(start)
+				.or_insert_with(|| Bar::new(start, &trade.ticker))
+				.add(trade);
+		}
+		let mut close = None;
+		bars
+			.into_values()
+			.filter_map(|bar| {
+				let candle = bar.finish(close)?;
+				close = Some(candle.close);
+				Some(candle)
+			})
+			.collect()
+	}
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BarDiff {
+	// In `remote` but not `local`
+	Missing {
+		ts: Timestamp
+	},
+	// In `local` but not `remote`
+	Extra {
+		ts: Timestamp
+	},
+	Mismatch {
+		ts:     Timestamp,
+		field:  &'static str,
+		local:  f64,
+		remote: f64
+	}
+}
+
+// Differences between bars built locally and `AggResponse.results`, matched by start time.
+// Prices and VWAP may differ by `tolerance`. `num_ticks` is only compared when Polygon sent it.
+pub fn diff_bars(local: &[Candle], remote: &[Candle], tolerance: f64) -> Vec<BarDiff> {
+	let local_by_ts = local.iter().map(|c| (c.ts, c)).collect::<BTreeMap<_, _>>();
+	let remote_by_ts = remote.iter().map(|c| (c.ts, c)).collect::<BTreeMap<_, _>>();
+	let mut diffs = Vec::new();
+
+	for (ts, r) in remote_by_ts.iter() {
+		let l = match local_by_ts.get(ts) {
+			Some(l) => l,
+			None => {
+				diffs.push(BarDiff::Missing { ts: *ts });
+				continue;
+			}
+		};
+		let mut fields = vec![
+			("open", l.open, r.open, tolerance),
+			("high", l.high, r.high, tolerance),
+			("low", l.low, r.low, tolerance),
+			("close", l.close, r.close, tolerance),
+			("volume", l.volume, r.volume, 0.0),
+			("vwap", l.vwap as f64, r.vwap as f64, tolerance),
+		];
+		if r.num_ticks != super::default_num_ticks() {
+			fields.push(("num_ticks", l.num_ticks as f64, r.num_ticks as f64, 0.0));
+		}
+		for (field, local, remote, tolerance) in fields {
+			let same = (local.is_nan() && remote.is_nan()) || (local - remote).abs() <= tolerance;
+			if !same {
+				diffs.push(BarDiff::Mismatch {
+					ts: *ts,
+					field,
+					local,
+					remote
+				});
+			}
+		}
+	}
+	for ts in local_by_ts
+		.keys()
+		.filter(|ts| !remote_by_ts.contains_key(ts))
+	{
+		diffs.push(BarDiff::Extra { ts: *ts });
+	}
+
+	diffs
+}
+
+#[cfg(test)]
+mod bars {
+	use super::{diff_bars, BarBuilder, BarDiff};
+	use crate::{core::aggs::Timespan, equities::trades::Trade, test_trade};
+	use chrono::TimeZone;
+	use chrono_tz::America::New_York;
+
+	// 2020-01-02 09:30:00 EST
+	const OPEN: i64 = 1577975400000000000;
+	const MINUTE: i64 = 60_000_000_000;
+
+	fn trade(nanos: i64, price: f64, size: u32, conditions: Vec<u8>) -> Trade {
+		Trade {
+			price,
+			size,
+			conditions,
+			..test_trade::trade(nanos)
+		}
+	}
+
+	fn trades() -> Vec<Trade> {
+		vec![
+			trade(OPEN, 10.0, 100, vec![]),
+			// Odd lot: volume only
+			trade(OPEN + 1, 12.0, 10, vec![37]),
+			trade(OPEN + 2, 11.0, 100, vec![14]),
+			// Out of sequence: no open/close
+			trade(OPEN + 3, 9.0, 100, vec![31]),
+			trade(OPEN + MINUTE, 20.0, 50, vec![]),
+		]
+	}
+
+	#[test]
+	fn batch() {
+		let mut trades = trades();
+		trades.reverse();
+		let bars = BarBuilder::new(1, Timespan::Minute).build(&trades);
+		assert_eq!(bars.len(), 2);
+		let bar = &bars[0];
+		assert_eq!(bar.ts.to_string(), "2020-01-02 09:30:00.000000000 EST");
+		assert_eq!(
+			(bar.open, bar.high, bar.low, bar.close),
+			(10.0, 11.0, 9.0, 11.0)
+		);
+		assert_eq!(bar.volume, 310.0);
+		assert_eq!(bar.num_ticks, 4);
+		assert_eq!(bar.vwap, (1000.0 + 120.0 + 1100.0 + 900.0) as f32 / 310.0);
+	}
+
+	#[test]
+	fn incremental() {
+		let mut builder = BarBuilder::new(1, Timespan::Minute);
+		let mut bars = trades()
+			.iter()
+			.filter_map(|t| builder.push(t))
+			.collect::<Vec<_>>();
+		assert_eq!(bars.len(), 1);
+		bars.extend(builder.finish());
+
+		let batch = BarBuilder::new(1, Timespan::Minute).build(&trades());
+		assert_eq!(diff_bars(&bars, &batch, 0.0), vec![]);
+		assert!(builder.push(&trade(OPEN, 1.0, 1, vec![])).is_none());
+		assert!(builder
+			.push(&trade(OPEN + 2 * MINUTE, 1.0, 1, vec![]))
+			.is_some());
+		assert!(builder.push(&trade(OPEN, 1.0, 1, vec![])).is_none());
+		assert_eq!(builder.late, 1);
+	}
+
+	#[test]
+	fn alignment() {
+		let trades = trades();
+		let starts = |multiplier, timespan| {
+			BarBuilder::new(multiplier, timespan)
+				.build(&trades)
+				.iter()
+				.map(|c| c.ts.to_string())
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(starts(5, Timespan::Minute), vec![
+			"2020-01-02 09:30:00.000000000 EST"
+		]);
+		assert_eq!(starts(4, Timespan::Hour), vec![
+			"2020-01-02 07:00:00.000000000 EST"
+		]);
+		assert_eq!(starts(1, Timespan::Day), vec![
+			"2020-01-02 00:00:00.000000000 EST"
+		]);
+		assert_eq!(starts(1, Timespan::Week), vec![
+			"2019-12-29 00:00:00.000000000 EST"
+		]);
+		assert_eq!(starts(1, Timespan::Quarter), vec![
+			"2020-01-01 00:00:00.000000000 EST"
+		]);
+	}
+
+	#[test]
+	fn dst_alignment() {
+		let starts = |multiplier, timespan, times: &[(u32, u32, u32)]| {
+			let trades = times
+				.iter()
+				.map(|(m, d, h)| {
+					let time = New_York.with_ymd_and_hms(2020, *m, *d, *h, 0, 0).unwrap();
+					trade(time.timestamp_nanos_opt().unwrap(), 10.0, 100, vec![])
+				})
+				.collect::<Vec<_>>();
+			BarBuilder::new(multiplier, timespan)
+				.build(&trades)
+				.iter()
+				.map(|c| c.ts.to_string())
+				.collect::<Vec<_>>()
+		};
+		// Summer time
+		assert_eq!(starts(1, Timespan::Day, &[(7, 2, 10)]), vec![
+			"2020-07-02 00:00:00.000000000 EDT"
+		]);
+		assert_eq!(starts(1, Timespan::Week, &[(7, 2, 10)]), vec![
+			"2020-06-28 00:00:00.000000000 EDT"
+		]);
+		// Clocks went forward on Sunday 2020-03-08 and back on Sunday 2020-11-01
+		assert_eq!(
+			starts(1, Timespan::Day, &[
+				(3, 6, 10),
+				(3, 9, 10),
+				(10, 30, 10),
+				(11, 2, 10)
+			]),
+			vec![
+				"2020-03-06 00:00:00.000000000 EST",
+				"2020-03-09 00:00:00.000000000 EDT",
+				"2020-10-30 00:00:00.000000000 EDT",
+				"2020-11-02 00:00:00.000000000 EST"
+			]
+		);
+		assert_eq!(starts(1, Timespan::Week, &[(3, 9, 10), (11, 2, 10)]), vec![
+			"2020-03-08 00:00:00.000000000 EST",
+			"2020-11-01 00:00:00.000000000 EDT"
+		]);
+		// A two day bar spanning the change
+		assert_eq!(starts(2, Timespan::Day, &[(3, 8, 1), (3, 8, 10)]), vec![
+			"2020-03-07 00:00:00.000000000 EST"
+		]);
+	}
+
+	#[test]
+	fn diffs() {
+		let local = BarBuilder::new(1, Timespan::Minute).build(&trades());
+		let mut remote = BarBuilder::new(1, Timespan::Minute).build(&trades());
+		remote[0].close = 11.005;
+		let extra = remote.remove(1).ts;
+		assert_eq!(diff_bars(&local, &remote, 0.01), vec![BarDiff::Extra {
+			ts: extra
+		}]);
+		assert_eq!(diff_bars(&remote, &local, 0.0), vec![
+			BarDiff::Mismatch {
+				ts:     local[0].ts,
+				field:  "close",
+				local:  11.005,
+				remote: 11.0
+			},
+			BarDiff::Missing { ts: extra },
+		]);
+	}
+
+	#[test]
+	fn volume_only() {
+		let mut trades = trades();
+		// Odd lots alone after the last bar, which has a close to carry, and before the first,
+		// which doesn't
+		trades.push(trade(OPEN + 2 * MINUTE, 30.0, 10, vec![37]));
+		trades.push(trade(OPEN - MINUTE, 30.0, 10, vec![37]));
+		let bars = BarBuilder::new(1, Timespan::Minute).build(&trades);
+		assert_eq!(bars.len(), 3);
+		let bar = &bars[2];
+		assert_eq!(
+			(bar.open, bar.high, bar.low, bar.close),
+			(20.0, 20.0, 20.0, 20.0)
+		);
+		assert_eq!((bar.volume, bar.vwap), (10.0, 30.0));
+
+		let mut builder = BarBuilder::new(1, Timespan::Minute);
+		let mut pushed = trades[..6]
+			.iter()
+			.filter_map(|t| builder.push(t))
+			.collect::<Vec<_>>();
+		pushed.extend(builder.finish());
+		assert_eq!(diff_bars(&pushed, &bars, 0.0), vec![]);
+	}
+}
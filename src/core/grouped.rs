@@ -2,13 +2,18 @@ extern crate serde_json;
 extern crate ureq;
 
 use super::Candle;
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
 use crate::{
 	client::{Client, Result},
-	helpers::make_params,
+	helpers::{make_params, Query},
+	range::{Range, RangeFilter},
+	time::TimeConversion,
 	with_param
 };
+use chrono::NaiveDate;
+use chrono_tz::{America::New_York, UTC};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -20,7 +25,10 @@ pub struct GroupedResponse {
 	pub results: Vec<Candle>,
 	// For debugging
 	pub status: String,
-	pub uri: Option<String>
+	pub uri: Option<String>,
+	// Rows dropped by a lenient `RangeCheck`
+	#[serde(skip)]
+	pub out_of_range: usize
 }
 
 #[derive(Debug)]
@@ -57,17 +65,50 @@ pub enum Market {
 	FX
 }
 
-pub struct GroupedParams<'a> {
-	pub params: HashMap<&'a str, String>
+#[derive(Clone, Debug, Default)]
+pub struct GroupedParams {
+	pub(crate) params: Query
 }
 
-impl<'a> GroupedParams<'a> {
+impl GroupedParams {
 	with_param!(unadjusted, bool);
 
-	pub fn new() -> Self {
-		Self {
-			params: HashMap::with_capacity(1)
-		}
+	pub fn new() -> Self { Self::default() }
+}
+
+impl AsRef<Query> for GroupedParams {
+	fn as_ref(&self) -> &Query { &self.params }
+}
+
+// Stocks trade in New York and crypto and forex trade around the clock in UTC
+fn grouped_range(market: &Market, date: NaiveDate) -> Option<Range> {
+	let zone = match market {
+		Market::Crypto | Market::FX => UTC,
+		_ => New_York
+	};
+	Range::for_date(date, zone)
+}
+
+fn grouped_uri(
+	api_uri: &str,
+	locale: Locale,
+	market: Market,
+	date: NaiveDate,
+	params: Option<&GroupedParams>
+) -> String {
+	format!(
+		"{}/v2/aggs/grouped/locale/{}/market/{}/{}{}",
+		api_uri,
+		format!("{:?}", locale).to_lowercase(),
+		format!("{:?}", market).to_lowercase(),
+		date,
+		make_params(params),
+	)
+}
+
+fn fix_grouped(resp: &mut GroupedResponse, conversion: TimeConversion) {
+	for candle in resp.results.iter_mut() {
+		candle.ts = candle.ts.with_zone(conversion.zone(&candle.symbol));
 	}
 }
 
@@ -76,19 +117,38 @@ impl Client {
 		&self,
 		locale: Locale,
 		market: Market,
-		date: &str,
-		params: Option<&HashMap<&str, String>>
+		date: NaiveDate,
+		params: Option<&GroupedParams>
 	) -> Result<GroupedResponse> {
-		let uri = format!(
-			"{}/v2/aggs/grouped/locale/{}/market/{}/{}{}",
-			self.api_uri,
-			format!("{:?}", locale).to_lowercase(),
-			format!("{:?}", market).to_lowercase(),
-			date,
-			make_params(params),
-		);
+		let range = grouped_range(&market, date);
+		let uri = grouped_uri(&self.api_uri, locale, market, date, params);
 
 		let mut resp = self.get_response::<GroupedResponse>(&uri)?;
+		fix_grouped(&mut resp, self.time_conversion);
+		resp.out_of_range =
+			RangeFilter::new(range, self.range_check).retain(&uri, &mut resp.results)?;
+		resp.uri = Some(uri);
+
+		Ok(resp)
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_grouped(
+		&self,
+		locale: Locale,
+		market: Market,
+		date: NaiveDate,
+		params: Option<&GroupedParams>
+	) -> Result<GroupedResponse> {
+		let range = grouped_range(&market, date);
+		let uri = grouped_uri(&self.api_uri, locale, market, date, params);
+
+		let mut resp = self.get_response::<GroupedResponse>(&uri).await?;
+		fix_grouped(&mut resp, self.time_conversion);
+		resp.out_of_range =
+			RangeFilter::new(range, self.range_check).retain(&uri, &mut resp.results)?;
 		resp.uri = Some(uri);
 
 		Ok(resp)
@@ -98,14 +158,23 @@ impl Client {
 #[cfg(test)]
 mod grouped {
 	use super::{GroupedParams, Locale, Market};
-	use crate::client::Client;
+	use crate::{
+		client::{Client, ClientBuilder},
+		range::RangeCheck
+	};
+	use chrono::NaiveDate;
 
 	#[test]
 	fn start() {
 		let client = Client::new().unwrap();
-		let params = GroupedParams::new().unadjusted(true).params;
+		let params = GroupedParams::new().unadjusted(true);
 		let grouped = client
-			.get_grouped(Locale::US, Market::Stocks, "2004-01-02", Some(&params))
+			.get_grouped(
+				Locale::US,
+				Market::Stocks,
+				NaiveDate::from_ymd_opt(2004, 1, 2).unwrap(),
+				Some(&params)
+			)
 			.unwrap();
 		assert_eq!(grouped.query_count, grouped.results_count);
 		assert_eq!(grouped.query_count, 7670);
@@ -114,11 +183,20 @@ mod grouped {
 
 	#[test]
 	fn no_bad_ranges() {
-		let client = Client::new().unwrap();
-		let params = GroupedParams::new().unadjusted(true).params;
+		// The default drops out-of-range rows, which would hide them
+		let client = ClientBuilder::from_env()
+			.range_check(RangeCheck::Strict)
+			.build()
+			.unwrap();
+		let params = GroupedParams::new().unadjusted(true);
 		for _ in 0..50 {
 			client
-				.get_grouped(Locale::US, Market::Stocks, "2004-01-02", Some(&params))
+				.get_grouped(
+					Locale::US,
+					Market::Stocks,
+					NaiveDate::from_ymd_opt(2004, 1, 2).unwrap(),
+					Some(&params)
+				)
 				.unwrap();
 		}
 	}
@@ -127,13 +205,13 @@ mod grouped {
 	fn no_garbage_tickers() {
 		let client = Client::new().unwrap();
 		let bad_dates = vec![
-			"2020-04-07",
-			"2020-04-08",
-			"2020-04-09",
-			"2020-04-13",
-			"2020-04-14",
+			NaiveDate::from_ymd_opt(2020, 4, 7).unwrap(),
+			NaiveDate::from_ymd_opt(2020, 4, 8).unwrap(),
+			NaiveDate::from_ymd_opt(2020, 4, 9).unwrap(),
+			NaiveDate::from_ymd_opt(2020, 4, 13).unwrap(),
+			NaiveDate::from_ymd_opt(2020, 4, 14).unwrap(),
 		];
-		let params = GroupedParams::new().unadjusted(true).params;
+		let params = GroupedParams::new().unadjusted(true);
 		for date in bad_dates {
 			let grouped = client
 				.get_grouped(Locale::US, Market::Stocks, date, Some(&params))
@@ -153,20 +231,20 @@ mod grouped {
 	fn no_missing_vw() {
 		let client = Client::new().unwrap();
 		let bad_dates = vec![
-			"2020-04-07",
-			"2020-04-08",
-			"2020-04-09",
-			"2020-04-13",
-			"2020-04-14",
+			NaiveDate::from_ymd_opt(2020, 4, 7).unwrap(),
+			NaiveDate::from_ymd_opt(2020, 4, 8).unwrap(),
+			NaiveDate::from_ymd_opt(2020, 4, 9).unwrap(),
+			NaiveDate::from_ymd_opt(2020, 4, 13).unwrap(),
+			NaiveDate::from_ymd_opt(2020, 4, 14).unwrap(),
 		];
-		let params = GroupedParams::new().unadjusted(true).params;
+		let params = GroupedParams::new().unadjusted(true);
 		for date in bad_dates {
 			let grouped = client
 				.get_grouped(Locale::US, Market::Stocks, date, Some(&params))
 				.unwrap();
 			let mut missing_vwap = false;
 			for candle in grouped.results {
-				if candle.volume > 0 && candle.vwap.is_nan() {
+				if candle.volume > 0.0 && candle.vwap.is_nan() {
 					eprintln!("Bad ticker {}", candle.symbol);
 					missing_vwap = true;
 				}
@@ -1,8 +1,18 @@
-extern crate serde_json;
-extern crate ureq;
-
 use super::Candle;
-use crate::client::{Client, Result};
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::{
+	client::{Client, Result},
+	crypto::CryptoTrade,
+	equities::{
+		nbbo::NBBO,
+		trades::{f64_to_u32, to_id, Trade}
+	},
+	forex::ForexQuote,
+	helpers::encode_segment,
+	time::{from_millis, TimeConversion, Timestamp}
+};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -18,24 +28,442 @@ pub struct PrevResponse {
 	pub uri: Option<String>
 }
 
+fn fix_prev(resp: &mut PrevResponse, symbol: &str, conversion: TimeConversion) {
+	let zone = conversion.zone(symbol);
+	for candle in resp.results.iter_mut() {
+		candle.ts = candle.ts.with_zone(zone);
+	}
+}
+
+// { T, c, e, f, i, p, q, r, s, t, x, y, z }
+#[derive(Debug, Deserialize)]
+struct WireTrade {
+	#[serde(rename = "T")]
+	symbol: String,
+	#[serde(rename = "c", default)]
+	conditions: Vec<u8>,
+	#[serde(rename = "e", default)]
+	correction: u8,
+	#[serde(rename = "f")]
+	time_trf: Option<Timestamp>,
+	#[serde(rename = "i", deserialize_with = "to_id", default)]
+	id: u64,
+	#[serde(rename = "p", default)]
+	price: f64,
+	#[serde(rename = "q")]
+	sequence_number: Option<u64>,
+	#[serde(rename = "r")]
+	trf: Option<u8>,
+	#[serde(rename = "s", deserialize_with = "f64_to_u32", default)]
+	size: u32,
+	#[serde(rename = "t")]
+	time: Timestamp,
+	#[serde(rename = "x")]
+	exchange: u8,
+	#[serde(rename = "y")]
+	time_participant: Option<Timestamp>,
+	#[serde(rename = "z")]
+	tape: u8
+}
+
+impl WireTrade {
+	fn into_trade(self, zone: Tz) -> Trade {
+		Trade {
+			sequence_number: self.sequence_number,
+			tape: self.tape,
+			id: self.id,
+			ticker: self.symbol,
+			time: self.time.with_zone(zone),
+			time_participant: self.time_participant.map(|t| t.with_zone(zone)),
+			time_trf: self.time_trf.map(|t| t.with_zone(zone)),
+			price: self.price,
+			size: self.size,
+			conditions: self.conditions,
+			correction: self.correction,
+			exchange: self.exchange,
+			trf: self.trf
+		}
+	}
+}
+
+// { T, P, S, X, p, s, x, t, y, f, q, z }
+#[derive(Debug, Deserialize)]
+struct WireQuote {
+	#[serde(rename = "T")]
+	symbol: String,
+	#[serde(rename = "P", default)]
+	ask_price: f32,
+	#[serde(rename = "S", default)]
+	ask_lots: u32,
+	#[serde(rename = "X", default)]
+	ask_exchange: u32,
+	#[serde(rename = "p", default)]
+	bid_price: f32,
+	#[serde(rename = "s", default)]
+	bid_lots: u32,
+	#[serde(rename = "x", default)]
+	bid_exchange: u32,
+	#[serde(rename = "t")]
+	ts: Timestamp,
+	#[serde(rename = "y")]
+	ts_participant: Option<Timestamp>,
+	#[serde(rename = "f")]
+	ts_trf: Option<Timestamp>,
+	#[serde(rename = "z")]
+	tape: u32
+}
+
+impl WireQuote {
+	fn into_nbbo(self, zone: Tz) -> NBBO {
+		NBBO {
+			ts: self.ts.with_zone(zone),
+			ts_participant: self.ts_participant.map(|t| t.with_zone(zone)),
+			ts_trf: self.ts_trf.map(|t| t.with_zone(zone)),
+			symbol: self.symbol,
+			bid_exchange: self.bid_exchange,
+			ask_exchange: self.ask_exchange,
+			bid_lots: self.bid_lots,
+			ask_lots: self.ask_lots,
+			bid_price: self.bid_price,
+			ask_price: self.ask_price,
+			tape: self.tape
+		}
+	}
+}
+
+#[derive(Debug, Deserialize)]
+struct LastResponse<T> {
+	results: T
+}
+
+// { price, size, exchange, cond1, cond2, cond3, cond4, timestamp }
+#[derive(Debug, Deserialize)]
+struct WireV1Trade {
+	price:     f64,
+	#[serde(deserialize_with = "f64_to_u32")]
+	size:      u32,
+	exchange:  u8,
+	#[serde(default)]
+	cond1:     u8,
+	#[serde(default)]
+	cond2:     u8,
+	#[serde(default)]
+	cond3:     u8,
+	#[serde(default)]
+	cond4:     u8,
+	#[serde(deserialize_with = "from_millis")]
+	timestamp: Timestamp
+}
+
+impl WireV1Trade {
+	// v1 doesn't send ids, tapes or sequence numbers
+	fn into_trade(self, symbol: &str, zone: Tz) -> Trade {
+		Trade {
+			sequence_number: None,
+			tape: 0,
+			id: 0,
+			ticker: symbol.to_string(),
+			time: self.timestamp.with_zone(zone),
+			time_participant: None,
+			time_trf: None,
+			price: self.price,
+			size: self.size,
+			// 0 for unused slots
+			conditions: [self.cond1, self.cond2, self.cond3, self.cond4]
+				.into_iter()
+				.filter(|c| *c != 0)
+				.collect(),
+			correction: 0,
+			exchange: self.exchange,
+			trf: None
+		}
+	}
+}
+
+// { askprice, asksize, askexchange, bidprice, bidsize, bidexchange, timestamp }
+#[derive(Debug, Deserialize)]
+struct WireV1Quote {
+	askprice:    f32,
+	#[serde(deserialize_with = "f64_to_u32")]
+	asksize:     u32,
+	askexchange: u32,
+	bidprice:    f32,
+	#[serde(deserialize_with = "f64_to_u32")]
+	bidsize:     u32,
+	bidexchange: u32,
+	#[serde(deserialize_with = "from_millis")]
+	timestamp:   Timestamp
+}
+
+impl WireV1Quote {
+	fn into_nbbo(self, symbol: &str, zone: Tz) -> NBBO {
+		NBBO {
+			ts: self.timestamp.with_zone(zone),
+			ts_participant: None,
+			ts_trf: None,
+			symbol: symbol.to_string(),
+			bid_exchange: self.bidexchange,
+			ask_exchange: self.askexchange,
+			bid_lots: self.bidsize,
+			ask_lots: self.asksize,
+			bid_price: self.bidprice,
+			ask_price: self.askprice,
+			tape: 0
+		}
+	}
+}
+
+// { conditions, exchange, price, size, timestamp }. Open/close sends { c, x, p, s, t, i }.
+#[derive(Debug, Deserialize)]
+pub(crate) struct WireCryptoTrade {
+	#[serde(alias = "c", default)]
+	conditions: Vec<u8>,
+	#[serde(alias = "x")]
+	exchange:   u8,
+	#[serde(alias = "p")]
+	price:      f64,
+	#[serde(alias = "s")]
+	size:       f64,
+	#[serde(alias = "t", deserialize_with = "from_millis")]
+	timestamp:  Timestamp,
+	// Exchange's own, not always numeric
+	#[serde(alias = "i", default)]
+	id:         String
+}
+
+// { ask, bid, exchange, timestamp }
+#[derive(Debug, Deserialize)]
+struct WireForexQuote {
+	ask:       f64,
+	bid:       f64,
+	exchange:  u8,
+	#[serde(deserialize_with = "from_millis")]
+	timestamp: Timestamp
+}
+
+// Also sends { status, symbol }
+#[derive(Debug, Deserialize)]
+struct LastV1Response<T> {
+	last: T
+}
+
+// Crypto and forex symbols as used elsewhere, like "X:BTCUSD" and "C:EURUSD"
+pub(crate) fn crypto_symbol(from: &str, to: &str) -> String { format!("X:{}{}", from, to) }
+
+fn forex_symbol(from: &str, to: &str) -> String { format!("C:{}{}", from, to) }
+
+pub(crate) fn fix_crypto_trade(last: WireCryptoTrade, pair: String, zone: Tz) -> CryptoTrade {
+	CryptoTrade {
+		pair,
+		exchange: last.exchange,
+		id: last.id,
+		price: last.price,
+		size: last.size,
+		conditions: last.conditions,
+		time: last.timestamp.with_zone(zone),
+		received: None
+	}
+}
+
+fn fix_forex_quote(last: WireForexQuote, pair: String, zone: Tz) -> ForexQuote {
+	ForexQuote {
+		pair,
+		exchange: last.exchange,
+		bid: last.bid,
+		ask: last.ask,
+		time: last.timestamp.with_zone(zone)
+	}
+}
+
+fn last_trade_uri(api_uri: &str, symbol: &str) -> String {
+	format!("{}/v2/last/trade/{}", api_uri, encode_segment(symbol))
+}
+
+fn last_quote_uri(api_uri: &str, symbol: &str) -> String {
+	format!("{}/v2/last/nbbo/{}", api_uri, encode_segment(symbol))
+}
+
+fn last_trade_v1_uri(api_uri: &str, symbol: &str) -> String {
+	format!("{}/v1/last/stocks/{}", api_uri, encode_segment(symbol))
+}
+
+fn last_quote_v1_uri(api_uri: &str, symbol: &str) -> String {
+	format!(
+		"{}/v1/last_quote/stocks/{}",
+		api_uri,
+		encode_segment(symbol)
+	)
+}
+
+fn last_crypto_trade_uri(api_uri: &str, from: &str, to: &str) -> String {
+	format!(
+		"{}/v1/last/crypto/{}/{}",
+		api_uri,
+		encode_segment(from),
+		encode_segment(to)
+	)
+}
+
+fn last_forex_quote_uri(api_uri: &str, from: &str, to: &str) -> String {
+	format!(
+		"{}/v1/last_quote/currencies/{}/{}",
+		api_uri,
+		encode_segment(from),
+		encode_segment(to)
+	)
+}
+
 impl Client {
 	pub fn get_prev(&self, symbol: &str) -> Result<PrevResponse> {
-		let uri = format!("{}/v2/aggs/ticker/{}/prev", self.api_uri, symbol);
+		let uri = format!(
+			"{}/v2/aggs/ticker/{}/prev",
+			self.api_uri,
+			encode_segment(symbol)
+		);
 
 		let mut resp = self.get_response::<PrevResponse>(&uri)?;
 		resp.uri = Some(uri);
+		fix_prev(&mut resp, symbol, self.time_conversion);
 
-		let is_equity = !symbol.contains(":");
-		// Polygon returns GMT milliseconds for this endpoint
-		if is_equity {
-			// Subtract 5h of milliseconds
-			resp.results[0].ts -= 5 * 60 * 60 * 1_000;
-		}
-		// Convert to ns
-		resp.results[0].ts *= 1_000_000;
+		Ok(resp)
+	}
+
+	pub fn get_last_trade(&self, symbol: &str) -> Result<Trade> {
+		let uri = last_trade_uri(&self.api_uri, symbol);
+
+		let resp = self.get_response::<LastResponse<WireTrade>>(&uri)?;
+		Ok(resp.results.into_trade(self.time_conversion.zone(symbol)))
+	}
+
+	pub fn get_last_quote(&self, symbol: &str) -> Result<NBBO> {
+		let uri = last_quote_uri(&self.api_uri, symbol);
+
+		let resp = self.get_response::<LastResponse<WireQuote>>(&uri)?;
+		Ok(resp.results.into_nbbo(self.time_conversion.zone(symbol)))
+	}
+
+	// Older and with less detail than `get_last_trade`: no id, tape or sequence number
+	pub fn get_last_trade_v1(&self, symbol: &str) -> Result<Trade> {
+		let uri = last_trade_v1_uri(&self.api_uri, symbol);
+
+		let resp = self.get_response::<LastV1Response<WireV1Trade>>(&uri)?;
+		Ok(
+			resp
+				.last
+				.into_trade(symbol, self.time_conversion.zone(symbol))
+		)
+	}
+
+	// Older and with less detail than `get_last_quote`: no tape or participant timestamps
+	pub fn get_last_quote_v1(&self, symbol: &str) -> Result<NBBO> {
+		let uri = last_quote_v1_uri(&self.api_uri, symbol);
+
+		let resp = self.get_response::<LastV1Response<WireV1Quote>>(&uri)?;
+		Ok(
+			resp
+				.last
+				.into_nbbo(symbol, self.time_conversion.zone(symbol))
+		)
+	}
+
+	// Like "BTC", "USD". The trade's pair is "X:BTCUSD".
+	pub fn get_last_crypto_trade(&self, from: &str, to: &str) -> Result<CryptoTrade> {
+		let uri = last_crypto_trade_uri(&self.api_uri, from, to);
+		let symbol = crypto_symbol(from, to);
+		let zone = self.time_conversion.zone(&symbol);
+
+		let resp = self.get_response::<LastV1Response<WireCryptoTrade>>(&uri)?;
+		Ok(fix_crypto_trade(resp.last, symbol, zone))
+	}
+
+	// Like "EUR", "USD". The quote's symbol is "C:EURUSD" and sizes are 0.
+	pub fn get_last_forex_quote(&self, from: &str, to: &str) -> Result<ForexQuote> {
+		let uri = last_forex_quote_uri(&self.api_uri, from, to);
+		let symbol = forex_symbol(from, to);
+		let zone = self.time_conversion.zone(&symbol);
+
+		let resp = self.get_response::<LastV1Response<WireForexQuote>>(&uri)?;
+		Ok(fix_forex_quote(resp.last, symbol, zone))
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_prev(&self, symbol: &str) -> Result<PrevResponse> {
+		let uri = format!(
+			"{}/v2/aggs/ticker/{}/prev",
+			self.api_uri,
+			encode_segment(symbol)
+		);
+
+		let mut resp = self.get_response::<PrevResponse>(&uri).await?;
+		resp.uri = Some(uri);
+		fix_prev(&mut resp, symbol, self.time_conversion);
 
 		Ok(resp)
 	}
+
+	pub async fn get_last_trade(&self, symbol: &str) -> Result<Trade> {
+		let uri = last_trade_uri(&self.api_uri, symbol);
+
+		let resp = self.get_response::<LastResponse<WireTrade>>(&uri).await?;
+		Ok(resp.results.into_trade(self.time_conversion.zone(symbol)))
+	}
+
+	pub async fn get_last_quote(&self, symbol: &str) -> Result<NBBO> {
+		let uri = last_quote_uri(&self.api_uri, symbol);
+
+		let resp = self.get_response::<LastResponse<WireQuote>>(&uri).await?;
+		Ok(resp.results.into_nbbo(self.time_conversion.zone(symbol)))
+	}
+
+	pub async fn get_last_trade_v1(&self, symbol: &str) -> Result<Trade> {
+		let uri = last_trade_v1_uri(&self.api_uri, symbol);
+
+		let resp = self
+			.get_response::<LastV1Response<WireV1Trade>>(&uri)
+			.await?;
+		Ok(
+			resp
+				.last
+				.into_trade(symbol, self.time_conversion.zone(symbol))
+		)
+	}
+
+	pub async fn get_last_quote_v1(&self, symbol: &str) -> Result<NBBO> {
+		let uri = last_quote_v1_uri(&self.api_uri, symbol);
+
+		let resp = self
+			.get_response::<LastV1Response<WireV1Quote>>(&uri)
+			.await?;
+		Ok(
+			resp
+				.last
+				.into_nbbo(symbol, self.time_conversion.zone(symbol))
+		)
+	}
+
+	pub async fn get_last_crypto_trade(&self, from: &str, to: &str) -> Result<CryptoTrade> {
+		let uri = last_crypto_trade_uri(&self.api_uri, from, to);
+		let symbol = crypto_symbol(from, to);
+		let zone = self.time_conversion.zone(&symbol);
+
+		let resp = self
+			.get_response::<LastV1Response<WireCryptoTrade>>(&uri)
+			.await?;
+		Ok(fix_crypto_trade(resp.last, symbol, zone))
+	}
+
+	pub async fn get_last_forex_quote(&self, from: &str, to: &str) -> Result<ForexQuote> {
+		let uri = last_forex_quote_uri(&self.api_uri, from, to);
+		let symbol = forex_symbol(from, to);
+		let zone = self.time_conversion.zone(&symbol);
+
+		let resp = self
+			.get_response::<LastV1Response<WireForexQuote>>(&uri)
+			.await?;
+		Ok(fix_forex_quote(resp.last, symbol, zone))
+	}
 }
 
 #[cfg(test)]
@@ -49,3 +477,99 @@ mod prev {
 		assert_eq!(prev.results.len(), 1);
 	}
 }
+
+#[cfg(test)]
+mod last {
+	use crate::test_server::{StubResponse, StubServer};
+	use chrono_tz::{America::New_York, UTC};
+
+	#[test]
+	fn trade_and_quote() {
+		let trade = r#"{"status":"OK","request_id":"x","results":{"T":"AAPL","c":[37],
+			"f":1617901342969796400,"i":"71675577320245","p":129.8473,"q":3135876,"r":202,"s":25,
+			"t":1617901342969834000,"x":4,"y":1617901342968000000,"z":3}}"#;
+		let quote = r#"{"status":"OK","request_id":"x","results":{"P":127.98,"S":7,"T":"AAPL",
+			"X":19,"p":127.96,"q":83480742,"s":1,"t":1617827221349730300,"x":11,"y":1617827221349366000,
+			"z":3}}"#;
+		let server = StubServer::new(vec![
+			StubResponse::new(200, trade),
+			StubResponse::new(200, quote),
+		]);
+		let client = server.client();
+
+		let trade = client.get_last_trade("AAPL").unwrap();
+		assert!(server.request(0).starts_with("GET /v2/last/trade/AAPL "));
+		assert_eq!(trade.time.nanos(), 1617901342969834000);
+		assert_eq!(trade.time.zone(), New_York);
+		assert_eq!(trade.sequence_number, Some(3135876));
+		assert_eq!(trade.trf, Some(202));
+		assert_eq!(trade.id, 71675577320245);
+
+		let quote = client.get_last_quote("AAPL").unwrap();
+		assert!(server.request(1).starts_with("GET /v2/last/nbbo/AAPL "));
+		assert_eq!(quote.symbol, "AAPL");
+		assert_eq!((quote.bid_exchange, quote.ask_exchange), (11, 19));
+		assert_eq!((quote.bid_lots, quote.ask_lots), (1, 7));
+	}
+
+	#[test]
+	fn trade_and_quote_v1() {
+		let trade = r#"{"status":"success","symbol":"AAPL","last":{"price":159.59,"size":20,
+			"exchange":11,"cond1":14,"cond2":12,"cond3":0,"cond4":0,"timestamp":1518086464720}}"#;
+		let quote = r#"{"status":"success","symbol":"AAPL","last":{"askprice":159.59,"asksize":2,
+			"askexchange":11,"bidprice":159.45,"bidsize":20,"bidexchange":12,"timestamp":1518086601843}}"#;
+		let server = StubServer::new(vec![
+			StubResponse::new(200, trade),
+			StubResponse::new(200, quote),
+		]);
+		let client = server.client();
+
+		let trade = client.get_last_trade_v1("AAPL").unwrap();
+		assert!(server.request(0).starts_with("GET /v1/last/stocks/AAPL "));
+		assert_eq!(trade.ticker, "AAPL");
+		assert_eq!(trade.time.nanos(), 1518086464720000000);
+		assert_eq!(trade.time.zone(), New_York);
+		assert_eq!(trade.conditions, vec![14, 12]);
+		assert_eq!((trade.price, trade.size, trade.exchange), (159.59, 20, 11));
+
+		let quote = client.get_last_quote_v1("AAPL").unwrap();
+		assert!(server
+			.request(1)
+			.starts_with("GET /v1/last_quote/stocks/AAPL "));
+		assert_eq!((quote.bid_exchange, quote.ask_exchange), (12, 11));
+		assert_eq!((quote.bid_lots, quote.ask_lots), (20, 2));
+		assert_eq!(quote.ts.nanos(), 1518086601843000000);
+	}
+
+	#[test]
+	fn crypto_and_forex() {
+		let trade = r#"{"status":"success","symbol":"BTC-USD","last":{"conditions":[1],
+			"exchange":4,"price":16835.42,"size":0.006909,"timestamp":1605560885027}}"#;
+		let quote = r#"{"status":"success","symbol":"AUD/USD","last":{"ask":0.73124,
+			"bid":0.73122,"exchange":48,"timestamp":1605557756000}}"#;
+		let server = StubServer::new(vec![
+			StubResponse::new(200, trade),
+			StubResponse::new(200, quote),
+		]);
+		let client = server.client();
+
+		let trade = client.get_last_crypto_trade("BTC", "USD").unwrap();
+		assert!(server
+			.request(0)
+			.starts_with("GET /v1/last/crypto/BTC/USD "));
+		assert_eq!(trade.pair, "X:BTCUSD");
+		assert_eq!(trade.size, 0.006909);
+		assert_eq!(trade.time.nanos(), 1605560885027000000);
+		assert_eq!(trade.time.zone(), UTC);
+		assert!(trade.received.is_none());
+
+		let quote = client.get_last_forex_quote("AUD", "USD").unwrap();
+		assert!(server
+			.request(1)
+			.starts_with("GET /v1/last_quote/currencies/AUD/USD "));
+		assert_eq!(quote.pair, "C:AUDUSD");
+		assert_eq!((quote.bid, quote.ask), (0.73122, 0.73124));
+		assert_eq!(quote.exchange, 48);
+		assert_eq!(quote.time.zone(), UTC);
+	}
+}
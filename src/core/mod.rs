@@ -1,9 +1,12 @@
+use crate::{range::Timestamped, time::Timestamp};
 use serde::{de, Deserialize, Serialize};
 use std::fmt;
 
 pub mod aggs;
+pub mod bars;
 pub mod grouped;
 pub mod last;
+pub mod open_close;
 
 fn f64_to_u64<'de, D>(deserializer: D) -> Result<u64, D::Error>
 where
@@ -40,7 +43,7 @@ where
 	deserializer.deserialize_any(JsonNumberVisitor)
 }
 
-fn default_num_ticks() -> u64 { u64::MAX }
+pub(crate) fn default_num_ticks() -> u64 { u64::MAX }
 
 fn default_vwap() -> f32 { f32::NAN }
 // This is shared between these two structures:
@@ -50,8 +53,11 @@ fn default_vwap() -> f32 { f32::NAN }
 // { o, h, l, c, v, t, vw, n }
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Candle {
-	#[serde(rename(deserialize = "t"))]
-	pub ts:        i64,
+	#[serde(
+		rename(deserialize = "t"),
+		deserialize_with = "crate::time::from_millis"
+	)]
+	pub ts:        Timestamp,
 	#[serde(rename(deserialize = "T"), default)]
 	pub symbol:    String,
 	#[serde(rename(deserialize = "o"))]
@@ -62,8 +68,9 @@ pub struct Candle {
 	pub low:       f64,
 	#[serde(rename(deserialize = "c"))]
 	pub close:     f64,
-	#[serde(rename(deserialize = "v"), deserialize_with = "f64_to_u64")]
-	pub volume:    u64,
+	// Fractional for crypto
+	#[serde(rename(deserialize = "v"))]
+	pub volume:    f64,
 	#[serde(rename(deserialize = "vw"), default = "default_vwap")]
 	pub vwap:      f32,
 	#[serde(
@@ -74,6 +81,10 @@ pub struct Candle {
 	pub num_ticks: u64
 }
 
+impl Timestamped for Candle {
+	fn timestamp(&self) -> Timestamp { self.ts }
+}
+
 #[derive(Deserialize)]
 pub struct CandleResponse {
 	#[serde(default)] // On 2020-12-07 started being omitted instead of empty
@@ -0,0 +1,229 @@
+use super::{
+	f64_to_u64,
+	last::{crypto_symbol, fix_crypto_trade, WireCryptoTrade}
+};
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::{
+	client::{Client, Result},
+	crypto::CryptoTrade,
+	helpers::encode_segment,
+	time::TimeConversion
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+// Polygon's official open and close for a day. 404s (holidays, weekends and unknown symbols) are
+// `Error::EmptyResponse`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct DailyOpenClose {
+	pub symbol:      String,
+	// YYYY-MM-DD
+	pub from:        String,
+	pub open:        f64,
+	pub high:        f64,
+	pub low:         f64,
+	pub close:       f64,
+	#[serde(deserialize_with = "f64_to_u64")]
+	pub volume:      u64,
+	// Last trade before the open and after the close, if any
+	pub pre_market:  Option<f64>,
+	pub after_hours: Option<f64>,
+	// For debugging
+	pub status:      String,
+	pub uri:         Option<String>
+}
+
+// { symbol, isUTC, day, open, close, openTrades, closingTrades }
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WireCryptoOpenClose {
+	day: String,
+	open: f64,
+	close: f64,
+	#[serde(default)]
+	open_trades: Vec<WireCryptoTrade>,
+	#[serde(default)]
+	closing_trades: Vec<WireCryptoTrade>
+}
+
+// A UTC day's open and close and the trades that set them, with fractional sizes and the
+// exchanges' own ids
+#[derive(Debug, Serialize)]
+pub struct CryptoOpenClose {
+	// Like "X:BTCUSD"
+	pub symbol: String,
+	// Like "2020-10-09T00:00:00.000Z"
+	pub day: String,
+	pub open: f64,
+	pub close: f64,
+	pub open_trades: Vec<CryptoTrade>,
+	pub closing_trades: Vec<CryptoTrade>,
+	pub uri: Option<String>
+}
+
+fn open_close_uri(api_uri: &str, symbol: &str, date: NaiveDate) -> String {
+	format!(
+		"{}/v1/open-close/{}/{}",
+		api_uri,
+		encode_segment(symbol),
+		date.format("%Y-%m-%d")
+	)
+}
+
+fn crypto_open_close_uri(api_uri: &str, from: &str, to: &str, date: NaiveDate) -> String {
+	format!(
+		"{}/v1/open-close/crypto/{}/{}/{}",
+		api_uri,
+		encode_segment(from),
+		encode_segment(to),
+		date.format("%Y-%m-%d")
+	)
+}
+
+fn fix_crypto_open_close(
+	resp: WireCryptoOpenClose,
+	symbol: String,
+	uri: String,
+	conversion: TimeConversion
+) -> CryptoOpenClose {
+	let zone = conversion.zone(&symbol);
+	let trades = |trades: Vec<WireCryptoTrade>| {
+		trades
+			.into_iter()
+			.map(|t| fix_crypto_trade(t, symbol.clone(), zone))
+			.collect()
+	};
+	CryptoOpenClose {
+		open_trades: trades(resp.open_trades),
+		closing_trades: trades(resp.closing_trades),
+		symbol,
+		day: resp.day,
+		open: resp.open,
+		close: resp.close,
+		uri: Some(uri)
+	}
+}
+
+impl Client {
+	pub fn get_open_close(&self, symbol: &str, date: NaiveDate) -> Result<DailyOpenClose> {
+		let uri = open_close_uri(&self.api_uri, symbol, date);
+
+		let mut resp = self.get_response::<DailyOpenClose>(&uri)?;
+		resp.uri = Some(uri);
+
+		Ok(resp)
+	}
+
+	// Like "BTC", "USD"
+	pub fn get_crypto_open_close(
+		&self,
+		from: &str,
+		to: &str,
+		date: NaiveDate
+	) -> Result<CryptoOpenClose> {
+		let uri = crypto_open_close_uri(&self.api_uri, from, to, date);
+
+		let resp = self.get_response::<WireCryptoOpenClose>(&uri)?;
+		Ok(fix_crypto_open_close(
+			resp,
+			crypto_symbol(from, to),
+			uri,
+			self.time_conversion
+		))
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_open_close(&self, symbol: &str, date: NaiveDate) -> Result<DailyOpenClose> {
+		let uri = open_close_uri(&self.api_uri, symbol, date);
+
+		let mut resp = self.get_response::<DailyOpenClose>(&uri).await?;
+		resp.uri = Some(uri);
+
+		Ok(resp)
+	}
+
+	pub async fn get_crypto_open_close(
+		&self,
+		from: &str,
+		to: &str,
+		date: NaiveDate
+	) -> Result<CryptoOpenClose> {
+		let uri = crypto_open_close_uri(&self.api_uri, from, to, date);
+
+		let resp = self.get_response::<WireCryptoOpenClose>(&uri).await?;
+		Ok(fix_crypto_open_close(
+			resp,
+			crypto_symbol(from, to),
+			uri,
+			self.time_conversion
+		))
+	}
+}
+
+#[cfg(test)]
+mod open_close {
+	use crate::{
+		client::Error,
+		test_server::{StubResponse, StubServer}
+	};
+	use chrono::NaiveDate;
+
+	#[test]
+	fn stocks() {
+		let body = r#"{"afterHours":322.1,"close":325.12,"from":"2023-01-09","high":326.2,
+			"low":322.3,"open":324.66,"preMarket":324.5,"status":"OK","symbol":"AAPL",
+			"volume":26122646}"#;
+		let server = StubServer::new(vec![StubResponse::new(200, body)]);
+		let date = NaiveDate::from_ymd_opt(2023, 1, 9).unwrap();
+		let resp = server.client().get_open_close("AAPL", date).unwrap();
+		assert!(server
+			.request(0)
+			.starts_with("GET /v1/open-close/AAPL/2023-01-09 "));
+		assert_eq!(resp.from, "2023-01-09");
+		assert_eq!(resp.pre_market, Some(324.5));
+		assert_eq!(resp.after_hours, Some(322.1));
+		assert_eq!(resp.volume, 26122646);
+	}
+
+	#[test]
+	fn holiday() {
+		let body = r#"{"status":"NOT_FOUND","request_id":"x","message":"Data not found."}"#;
+		let server = StubServer::new(vec![StubResponse::new(404, body)]);
+		let date = NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+		match server.client().get_open_close("AAPL", date).unwrap_err() {
+			Error::EmptyResponse() => {}
+			e => panic!("bad error type {}", e)
+		};
+	}
+
+	#[test]
+	fn crypto() {
+		let body = r#"{"symbol":"BTC-USD","isUTC":true,"day":"2020-10-09T00:00:00.000Z",
+			"open":10932.44,"close":11050.64,
+			"openTrades":[{"s":0.002,"p":10932.44,"x":1,"t":1602201600056,"c":[2],"i":"511235746"}],
+			"closingTrades":[{"conditions":[1],"exchange":4,"price":11050.64,"size":0.5,
+			"timestamp":1602287999795,"i":"3f1c9a2e-8d4b-4e7a"}]}"#;
+		let server = StubServer::new(vec![StubResponse::new(200, body)]);
+		let date = NaiveDate::from_ymd_opt(2020, 10, 9).unwrap();
+		let resp = server
+			.client()
+			.get_crypto_open_close("BTC", "USD", date)
+			.unwrap();
+		assert!(server
+			.request(0)
+			.starts_with("GET /v1/open-close/crypto/BTC/USD/2020-10-09 "));
+		assert_eq!(resp.symbol, "X:BTCUSD");
+		let open = &resp.open_trades[0];
+		assert_eq!(open.time.nanos(), 1602201600056000000);
+		assert_eq!(open.size, 0.002);
+		assert_eq!(open.id, "511235746");
+		assert_eq!(open.pair, "X:BTCUSD");
+		let close = &resp.closing_trades[0];
+		assert_eq!(close.exchange, 4);
+		assert_eq!(close.id, "3f1c9a2e-8d4b-4e7a");
+	}
+}
@@ -0,0 +1,30 @@
+// Crypto types shared by the REST endpoints and the socket
+use crate::time::{from_millis, from_millis_opt, Timestamp};
+use serde::{Deserialize, Serialize};
+
+// Sent over the socket as { ev, pair, p, t, s, c, i, x, r }. REST responses are converted.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CryptoTrade {
+	// Like BTC-USD over the socket and X:BTCUSD from REST
+	pub pair:       String,
+	#[serde(rename(deserialize = "x"))]
+	pub exchange:   u8,
+	// Exchange's own, not always numeric
+	#[serde(rename(deserialize = "i"), default)]
+	pub id:         String,
+	#[serde(rename(deserialize = "p"))]
+	pub price:      f64,
+	#[serde(rename(deserialize = "s"))]
+	pub size:       f64,
+	#[serde(rename(deserialize = "c"), default)]
+	pub conditions: Vec<u8>,
+	#[serde(rename(deserialize = "t"), deserialize_with = "from_millis")]
+	pub time:       Timestamp,
+	// When Polygon received it. Only sent over the socket.
+	#[serde(
+		rename(deserialize = "r"),
+		deserialize_with = "from_millis_opt",
+		default
+	)]
+	pub received:   Option<Timestamp>
+}
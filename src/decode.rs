@@ -0,0 +1,223 @@
+use crate::client::Error;
+use serde::de::{
+	DeserializeOwned, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor
+};
+use std::{
+	fmt,
+	io::{self, BufReader, ErrorKind, Read},
+	marker::PhantomData
+};
+
+const SNIPPET_LEN: usize = 256;
+
+pub(crate) fn snippet(body: &str) -> String { body.chars().take(SNIPPET_LEN).collect() }
+
+// `body_reader` inflates gzip bodies with flate2's `GzDecoder` while they're decoded, so corrupt
+// or truncated ones surface here as read errors
+pub(crate) fn read_error(uri: &str, e: io::Error) -> backoff::Error<Error> {
+	match e.kind() {
+		ErrorKind::InvalidData | ErrorKind::InvalidInput => {
+			backoff::Error::permanent(Error::Decompress {
+				uri:    uri.to_string(),
+				source: e
+			})
+		}
+		_ => backoff::Error::transient(Error::IoError(e))
+	}
+}
+
+// Keeps the start of a body for error messages while streaming the rest
+struct HeadReader<R> {
+	inner: R,
+	head:  Vec<u8>
+}
+
+impl<R: Read> Read for HeadReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let n = self.inner.read(buf)?;
+		let room = SNIPPET_LEN.saturating_sub(self.head.len());
+		self.head.extend_from_slice(&buf[..n.min(room)]);
+		Ok(n)
+	}
+}
+
+impl<R> HeadReader<R> {
+	fn new(inner: R) -> Self {
+		Self {
+			inner,
+			head: Vec::with_capacity(SNIPPET_LEN)
+		}
+	}
+}
+
+fn stream_error(uri: &str, e: serde_json::Error, head: &[u8]) -> backoff::Error<Error> {
+	if e.is_io() {
+		return read_error(uri, e.into());
+	}
+	backoff::Error::permanent(Error::Decode {
+		uri: uri.to_string(),
+		body_snippet: snippet(&String::from_utf8_lossy(head)),
+		source: e
+	})
+}
+
+pub(crate) fn decode_reader<T: DeserializeOwned>(
+	uri: &str,
+	reader: impl Read
+) -> std::result::Result<T, backoff::Error<Error>> {
+	let mut reader = HeadReader::new(BufReader::new(reader));
+	serde_json::from_reader(&mut reader).map_err(|e| stream_error(uri, e, &reader.head))
+}
+
+// Everything in a list response besides `results`
+#[derive(Debug, Default)]
+pub struct PageInfo {
+	pub next_url:     Option<String>,
+	pub status:       Option<String>,
+	pub request_id:   Option<String>,
+	// Rows dropped by a lenient `RangeCheck`
+	pub out_of_range: usize
+}
+
+// Calls `on_row` for each of `results` as it's parsed so a page never has to fit in memory when
+// `reader` streams from the socket.
+// Errors after the first row are permanent since retrying would repeat rows.
+pub(crate) fn decode_rows<T: DeserializeOwned, F: FnMut(T)>(
+	uri: &str,
+	reader: impl Read,
+	on_row: &mut F
+) -> std::result::Result<PageInfo, backoff::Error<Error>> {
+	let mut reader = HeadReader::new(BufReader::new(reader));
+	let mut num_rows = 0;
+	let res = {
+		let mut de = serde_json::Deserializer::from_reader(&mut reader);
+		let mut count_row = |row: T| {
+			num_rows += 1;
+			on_row(row);
+		};
+		let seed = PageSeed {
+			on_row: &mut count_row,
+			row:    PhantomData
+		};
+		seed
+			.deserialize(&mut de)
+			.and_then(|page| de.end().map(|_| page))
+	};
+
+	res.map_err(|e| match stream_error(uri, e, &reader.head) {
+		backoff::Error::Transient { err, .. } if num_rows > 0 => backoff::Error::Permanent(err),
+		err => err
+	})
+}
+
+struct PageSeed<'a, T, F> {
+	on_row: &'a mut F,
+	row:    PhantomData<T>
+}
+
+impl<'de, T: DeserializeOwned, F: FnMut(T)> DeserializeSeed<'de> for PageSeed<'_, T, F> {
+	type Value = PageInfo;
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<PageInfo, D::Error> {
+		deserializer.deserialize_map(self)
+	}
+}
+
+impl<'de, T: DeserializeOwned, F: FnMut(T)> Visitor<'de> for PageSeed<'_, T, F> {
+	type Value = PageInfo;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a page of results")
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<PageInfo, A::Error> {
+		let on_row = self.on_row;
+		let mut page = PageInfo::default();
+		while let Some(key) = map.next_key::<String>()? {
+			match key.as_str() {
+				"results" => map.next_value_seed(RowsSeed {
+					on_row: &mut *on_row,
+					row:    PhantomData
+				})?,
+				"next_url" => page.next_url = map.next_value()?,
+				"status" => page.status = map.next_value()?,
+				"request_id" => page.request_id = map.next_value()?,
+				_ => {
+					map.next_value::<IgnoredAny>()?;
+				}
+			}
+		}
+		Ok(page)
+	}
+}
+
+struct RowsSeed<'a, T, F> {
+	on_row: &'a mut F,
+	row:    PhantomData<T>
+}
+
+impl<'de, T: DeserializeOwned, F: FnMut(T)> DeserializeSeed<'de> for RowsSeed<'_, T, F> {
+	type Value = ();
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+		deserializer.deserialize_seq(self)
+	}
+}
+
+impl<'de, T: DeserializeOwned, F: FnMut(T)> Visitor<'de> for RowsSeed<'_, T, F> {
+	type Value = ();
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a list of results")
+	}
+
+	fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+		while let Some(row) = seq.next_element::<T>()? {
+			(self.on_row)(row);
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod decode {
+	use super::{decode_reader, decode_rows};
+	use crate::client::Error;
+	use serde_json::Value;
+
+	#[test]
+	fn rows() {
+		let body = br#"{"results":[1,2,3],"status":"OK","next_url":"https://x/?cursor=a","count":3}"#;
+		let mut rows = Vec::<u32>::new();
+		let page = decode_rows("uri", &body[..], &mut |row| rows.push(row)).unwrap();
+		assert_eq!(rows, vec![1, 2, 3]);
+		assert_eq!(page.next_url.as_deref(), Some("https://x/?cursor=a"));
+		assert_eq!(page.status.as_deref(), Some("OK"));
+	}
+
+	#[test]
+	fn bad_row() {
+		let body = br#"{"results":[1,"two",3]}"#;
+		let mut rows = Vec::<u32>::new();
+		let err = decode_rows("uri", &body[..], &mut |row| rows.push(row)).unwrap_err();
+		assert_eq!(rows, vec![1]);
+		match err {
+			backoff::Error::Permanent(Error::Decode { body_snippet, .. }) => {
+				assert!(body.starts_with(body_snippet.as_bytes()))
+			}
+			_ => panic!("bad error type")
+		};
+	}
+
+	#[test]
+	fn snippet() {
+		let body = format!("{{\"results\":[{}x]}}", "1,".repeat(1000));
+		match decode_reader::<Value>("uri", body.as_bytes()).unwrap_err() {
+			backoff::Error::Permanent(Error::Decode { body_snippet, .. }) => {
+				assert_eq!(body_snippet.len(), 256);
+				assert!(body.starts_with(&body_snippet));
+			}
+			_ => panic!("bad error type")
+		};
+	}
+}
@@ -0,0 +1,182 @@
+// Which trades may update bars, following the consolidated (CTA/UTP) rules Polygon publishes at
+// /v3/reference/conditions. `ReferenceData` has the live table; this one works offline.
+use super::trades::Trade;
+use crate::reference::conditions::UpdateRule;
+use chrono::NaiveTime;
+use chrono_tz::America::New_York;
+
+const fn rule(
+	updates_high_low: bool,
+	updates_open_close: bool,
+	updates_volume: bool
+) -> UpdateRule {
+	UpdateRule {
+		updates_high_low,
+		updates_open_close,
+		updates_volume
+	}
+}
+
+const ALL: UpdateRule = rule(true, true, true);
+// Counted in volume only
+const VOLUME: UpdateRule = rule(false, false, true);
+const NONE: UpdateRule = rule(false, false, false);
+
+// Consolidated update rules for stock trade condition `id`. `None` for conditions that don't
+// restrict anything.
+pub fn condition_rule(id: u8) -> Option<UpdateRule> {
+	Some(match id {
+		// Average Price Trade
+		2 => VOLUME,
+		// Bunched Sold Trade
+		5 => rule(true, false, true),
+		// Cash Sale
+		7 => VOLUME,
+		// Derivatively Priced
+		10 => rule(true, false, true),
+		// Form T
+		12 => VOLUME,
+		// Extended Trading Hours (Sold Out of Sequence)
+		13 => VOLUME,
+		// Market Center Official Close and Open
+		15 | 16 => NONE,
+		// Next Day
+		20 => VOLUME,
+		// Price Variation Trade
+		21 => VOLUME,
+		// Prior Reference Price
+		22 => rule(true, false, true),
+		// Seller
+		28 => VOLUME,
+		// Sold (Out of Sequence), with and without Stopped Stock
+		31 | 32 => rule(true, false, true),
+		// Odd Lot Trade
+		37 => VOLUME,
+		// Corrected Consolidated Close
+		38 => rule(true, true, false),
+		// Contingent Trade and Qualified Contingent Trade
+		52 | 53 => VOLUME,
+		_ => return None
+	})
+}
+
+impl Trade {
+	// All conditions' rules combined. Corrected and cancelled trades update nothing.
+	fn rule(&self) -> UpdateRule {
+		if self.correction != 0 {
+			return NONE;
+		}
+		self
+			.conditions
+			.iter()
+			.filter_map(|c| condition_rule(*c))
+			.fold(ALL, |acc, r| UpdateRule {
+				updates_high_low:   acc.updates_high_low && r.updates_high_low,
+				updates_open_close: acc.updates_open_close && r.updates_open_close,
+				updates_volume:     acc.updates_volume && r.updates_volume
+			})
+	}
+
+	pub fn updates_high_low(&self) -> bool { self.rule().updates_high_low }
+
+	// Whether this can be a bar's open or close (the "last" price)
+	pub fn updates_open_close(&self) -> bool { self.rule().updates_open_close }
+
+	// Odd lots still count towards volume
+	pub fn updates_volume(&self) -> bool { self.rule().updates_volume }
+
+	// Updates every part of a bar
+	pub fn is_eligible(&self) -> bool {
+		let rule = self.rule();
+		rule.updates_high_low && rule.updates_open_close && rule.updates_volume
+	}
+
+	// 09:30 to 16:00 New York time. Doesn't know about weekends, holidays or early closes.
+	pub fn is_regular_hours(&self) -> bool {
+		let time = self.time.with_zone(New_York).to_datetime().time();
+		let open = NaiveTime::from_hms_opt(9, 30, 0).unwrap();
+		let close = NaiveTime::from_hms_opt(16, 0, 0).unwrap();
+		time >= open && time < close
+	}
+}
+
+// Skips trades that can't update a bar's open, close, high, low and volume: odd lots, average
+// price and out-of-sequence prints, corrections and so on
+pub struct Eligible<I> {
+	trades: I
+}
+
+impl<I: Iterator<Item = Trade>> Iterator for Eligible<I> {
+	type Item = Trade;
+
+	fn next(&mut self) -> Option<Trade> { self.trades.by_ref().find(|t| t.is_eligible()) }
+}
+
+pub trait EligibleExt: Iterator<Item = Trade> + Sized {
+	// `trades.into_iter().eligible()`
+	fn eligible(self) -> Eligible<Self> { Eligible { trades: self } }
+}
+
+impl<I: Iterator<Item = Trade>> EligibleExt for I {}
+
+#[cfg(test)]
+mod conditions {
+	use super::EligibleExt;
+	use crate::{equities::trades::Trade, test_trade};
+
+	fn trade(id: u64, conditions: Vec<u8>, correction: u8, nanos: i64) -> Trade {
+		Trade {
+			id,
+			conditions,
+			correction,
+			..test_trade::trade(nanos)
+		}
+	}
+
+	// 2020-01-02 09:30:00 EST
+	const OPEN: i64 = 1577975400000000000;
+
+	#[test]
+	fn rules() {
+		let regular = trade(1, vec![], 0, OPEN);
+		assert!(regular.is_eligible());
+		// Intermarket Sweep and Odd Lot
+		let odd_lot = trade(2, vec![14, 37], 0, OPEN);
+		assert!(!odd_lot.updates_high_low());
+		assert!(!odd_lot.updates_open_close());
+		assert!(odd_lot.updates_volume());
+		let out_of_sequence = trade(3, vec![31], 0, OPEN);
+		assert!(out_of_sequence.updates_high_low());
+		assert!(!out_of_sequence.updates_open_close());
+		let corrected = trade(4, vec![], 1, OPEN);
+		assert!(!corrected.updates_volume());
+	}
+
+	#[test]
+	fn regular_hours() {
+		assert!(trade(1, vec![], 0, OPEN).is_regular_hours());
+		assert!(!trade(1, vec![], 0, OPEN - 1).is_regular_hours());
+		// 15:59:59.999999999 and 16:00
+		let close = OPEN + 390 * 60 * 1_000_000_000;
+		assert!(trade(1, vec![], 0, close - 1).is_regular_hours());
+		assert!(!trade(1, vec![], 0, close).is_regular_hours());
+	}
+
+	#[test]
+	fn eligible() {
+		let trades = vec![
+			trade(1, vec![], 0, OPEN),
+			trade(2, vec![37], 0, OPEN),
+			trade(3, vec![2], 0, OPEN),
+			trade(4, vec![13], 0, OPEN),
+			trade(5, vec![14], 1, OPEN),
+			trade(6, vec![14], 0, OPEN),
+		];
+		let ids = trades
+			.into_iter()
+			.eligible()
+			.map(|t| t.id)
+			.collect::<Vec<_>>();
+		assert_eq!(ids, vec![1, 6]);
+	}
+}
@@ -1,2 +1,3 @@
+pub mod conditions;
 pub mod nbbo;
 pub mod trades;
@@ -1,26 +1,31 @@
 extern crate serde_json;
 extern crate ureq;
-use crate::client::Error;
+#[cfg(feature = "async")]
+use crate::{async_client::AsyncClient, paginator::AsyncPaginator};
 
 use crate::{
 	client::{Client, Result},
-	helpers::make_params,
-	with_param
+	decode::PageInfo,
+	helpers::{encode_segment, make_params, Order, Query},
+	paginator::Paginator,
+	range::{Range, RangeFilter, Timestamped},
+	time::{TimeConversion, Timestamp},
+	with_param, with_time_param
 };
+use chrono::NaiveDate;
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::io;
 
 const MAX_LIMIT: usize = 50_000;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NBBO {
 	#[serde(rename(deserialize = "sip_timestamp"))]
-	pub ts: i64,
+	pub ts: Timestamp,
 	#[serde(rename(deserialize = "participant_timestamp"))]
-	pub ts_participant: Option<i64>,
+	pub ts_participant: Option<Timestamp>,
 	#[serde(rename(deserialize = "trf_timestamp"))]
-	pub ts_trf: Option<i64>,
+	pub ts_trf: Option<Timestamp>,
 	#[serde(default)]
 	pub symbol: String,
 	pub bid_exchange: u32,
@@ -36,30 +41,38 @@ pub struct NBBO {
 	pub tape: u32
 }
 
+impl Timestamped for NBBO {
+	fn timestamp(&self) -> Timestamp { self.ts }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct NBBOsResponse {
 	pub results: Vec<NBBO>,
 	pub next_url: Option<String>,
-	pub status:   String, // For debugging
-	pub uri:     Option<String>
+	pub status: String, // For debugging
+	pub uri: Option<String>,
+	// Rows dropped by a lenient `RangeCheck`
+	#[serde(skip)]
+	pub out_of_range: usize
 }
 
-pub struct NBBOsParams<'a> {
-	pub params: HashMap<&'a str, String>
+#[derive(Clone, Debug, Default)]
+pub struct NBBOsParams {
+	pub(crate) params: Query
 }
 
-impl<'a> NBBOsParams<'a> {
-	with_param!(timestamp, &str);
+impl NBBOsParams {
+	with_time_param!(timestamp, "timestamp");
 
-	with_param!(timestamp_lt, &str);
+	with_time_param!(timestamp_lt, "timestamp.lt");
 
-	with_param!(timestamp_lte, &str);
+	with_time_param!(timestamp_lte, "timestamp.lte");
 
-	with_param!(timestamp_gt, &str);
+	with_time_param!(timestamp_gt, "timestamp.gt");
 
-	with_param!(timestamp_gte, &str);
+	with_time_param!(timestamp_gte, "timestamp.gte");
 
-	with_param!(order, &str);
+	with_param!(order, Order);
 
 	with_param!(reverse, bool);
 
@@ -68,74 +81,208 @@ impl<'a> NBBOsParams<'a> {
 	// Undocumented but appears in next_page_path
 	with_param!(cursor, &str);
 
-	pub fn new() -> Self {
-		Self {
-			params: HashMap::with_capacity(4)
-		}
+	pub fn new() -> Self { Self::default() }
+}
+
+impl AsRef<Query> for NBBOsParams {
+	fn as_ref(&self) -> &Query { &self.params }
+}
+
+fn quotes_uri(api_uri: &str, symbol: &str, params: Option<&NBBOsParams>) -> String {
+	format!(
+		"{}/v3/quotes/{}{}",
+		api_uri,
+		encode_segment(symbol),
+		make_params(params)
+	)
+}
+
+// Polygon reads dates in the symbol's exchange time
+fn quotes_range(symbol: &str, params: Option<&NBBOsParams>) -> Option<Range> {
+	Range::for_params(
+		params.map(|p| &p.params),
+		TimeConversion::Exchange.zone(symbol)
+	)
+}
+
+fn fix_quote(row: &mut NBBO, symbol: &str, zone: Tz) {
+	row.symbol = symbol.to_string();
+	row.ts = row.ts.with_zone(zone);
+	row.ts_participant = row.ts_participant.map(|t| t.with_zone(zone));
+	row.ts_trf = row.ts_trf.map(|t| t.with_zone(zone));
+}
+
+fn fix_nbbo(resp: &mut NBBOsResponse, symbol: &str, conversion: TimeConversion) {
+	let zone = conversion.zone(symbol);
+	for row in resp.results.iter_mut() {
+		fix_quote(row, symbol, zone);
 	}
 }
 
 impl Client {
-	pub fn get_nbbo(
-		&self,
-		symbol: &str,
-		params: Option<&HashMap<&str, String>>
-	) -> Result<NBBOsResponse> {
-		let uri = format!(
-			"{}/v3/quotes/{}{}",
-			self.api_uri,
-			symbol,
-			make_params(params),
-		);
+	pub fn get_nbbo(&self, symbol: &str, params: Option<&NBBOsParams>) -> Result<NBBOsResponse> {
+		let uri = quotes_uri(&self.api_uri, symbol, params);
 
 		let mut resp = self.get_response::<NBBOsResponse>(&uri)?;
+		fix_nbbo(&mut resp, symbol, self.time_conversion);
+		let filter = RangeFilter::new(quotes_range(symbol, params), self.range_check);
+		resp.out_of_range = filter.retain(&uri, &mut resp.results)?;
 		resp.uri = Some(uri);
 
-		// Polygon returns the exchange opening time in nanoseconds since epoch
-		for row in resp.results.iter_mut() {
-			row.symbol = symbol.to_string();
+		Ok(resp)
+	}
+
+	// Like `get_nbbo` but streams rows to `on_row` as they're parsed
+	pub fn get_nbbo_with(
+		&self,
+		symbol: &str,
+		params: Option<&NBBOsParams>,
+		mut on_row: impl FnMut(NBBO)
+	) -> Result<PageInfo> {
+		let uri = quotes_uri(&self.api_uri, symbol, params);
+		let zone = self.time_conversion.zone(symbol);
+		let mut filter = RangeFilter::new(quotes_range(symbol, params), self.range_check);
+
+		let mut page = self.get_rows(&uri, |mut row: NBBO| {
+			fix_quote(&mut row, symbol, zone);
+			if filter.keep(&row) {
+				on_row(row);
+			}
+		})?;
+		page.out_of_range = filter.finish(&uri)?;
+
+		Ok(page)
+	}
+
+	pub fn paginate_nbbo(&self, symbol: &str, params: Option<&NBBOsParams>) -> Paginator<'_, NBBO> {
+		let zone = self.time_conversion.zone(symbol);
+		let range = quotes_range(symbol, params);
+		let symbol = symbol.to_string();
+		self
+			.paginate(quotes_uri(&self.api_uri, &symbol, params))
+			.with_fix(move |row: &mut NBBO| fix_quote(row, &symbol, zone))
+			.with_range(range, self.range_check)
+	}
+
+	pub fn get_all_nbbo(&self, symbol: &str, date: NaiveDate) -> Result<Vec<NBBO>> {
+		let params = NBBOsParams::new().limit(MAX_LIMIT).timestamp(date);
+		let mut res = Vec::<NBBO>::new();
+		for page in self.paginate_nbbo(symbol, Some(&params)) {
+			res.extend(page?);
 		}
 
+		Ok(res)
+	}
+
+	// Like `get_all_nbbo` but never holds more than one row in memory
+	pub fn get_all_nbbo_with(
+		&self,
+		symbol: &str,
+		date: NaiveDate,
+		on_row: impl FnMut(NBBO)
+	) -> Result<()> {
+		let params = NBBOsParams::new().limit(MAX_LIMIT).timestamp(date);
+		self
+			.paginate_nbbo(symbol, Some(&params))
+			.for_each_row(on_row)
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_nbbo(
+		&self,
+		symbol: &str,
+		params: Option<&NBBOsParams>
+	) -> Result<NBBOsResponse> {
+		let uri = quotes_uri(&self.api_uri, symbol, params);
+
+		let mut resp = self.get_response::<NBBOsResponse>(&uri).await?;
+		fix_nbbo(&mut resp, symbol, self.time_conversion);
+		let filter = RangeFilter::new(quotes_range(symbol, params), self.range_check);
+		resp.out_of_range = filter.retain(&uri, &mut resp.results)?;
+		resp.uri = Some(uri);
+
 		Ok(resp)
 	}
 
-	pub fn get_all_nbbo(&self, symbol: &str, date: &str) -> Result<Vec<NBBO>> {
-		let mut params = NBBOsParams::new().limit(MAX_LIMIT).timestamp(date);
-		let mut res = Vec::<NBBO>::new();
-		loop {
-			let page = self.get_nbbo(symbol, Some(&params.params))?;
-			res.extend(page.results.into_iter());
-			match page.next_url {
-				Some(next_url) => {
-					let split = next_url.split("cursor=").collect::<Vec<&str>>();
-					if split.len() != 2 {
-						let msg = format!("no cursor in next_url {}", next_url);
-						let io_error = io::Error::new(io::ErrorKind::UnexpectedEof, msg);
-						return Err(Error::IoError(io_error));
-					}
-					let cursor = split[1];
-					params = NBBOsParams::new().cursor(cursor);
+	// Like `get_nbbo` but passes rows to `on_row` instead of collecting them
+	pub async fn get_nbbo_with(
+		&self,
+		symbol: &str,
+		params: Option<&NBBOsParams>,
+		mut on_row: impl FnMut(NBBO)
+	) -> Result<PageInfo> {
+		let uri = quotes_uri(&self.api_uri, symbol, params);
+		let zone = self.time_conversion.zone(symbol);
+		let mut filter = RangeFilter::new(quotes_range(symbol, params), self.range_check);
+
+		let mut page = self
+			.get_rows(&uri, |mut row: NBBO| {
+				fix_quote(&mut row, symbol, zone);
+				if filter.keep(&row) {
+					on_row(row);
 				}
-				None => break
-			};
+			})
+			.await?;
+		page.out_of_range = filter.finish(&uri)?;
+
+		Ok(page)
+	}
+
+	pub fn paginate_nbbo(
+		&self,
+		symbol: &str,
+		params: Option<&NBBOsParams>
+	) -> AsyncPaginator<'_, NBBO> {
+		let zone = self.time_conversion.zone(symbol);
+		let range = quotes_range(symbol, params);
+		let symbol = symbol.to_string();
+		self
+			.paginate(quotes_uri(&self.api_uri, &symbol, params))
+			.with_fix(move |row: &mut NBBO| fix_quote(row, &symbol, zone))
+			.with_range(range, self.range_check)
+	}
+
+	pub async fn get_all_nbbo(&self, symbol: &str, date: NaiveDate) -> Result<Vec<NBBO>> {
+		let params = NBBOsParams::new().limit(MAX_LIMIT).timestamp(date);
+		let mut pages = self.paginate_nbbo(symbol, Some(&params));
+		let mut res = Vec::<NBBO>::new();
+		while let Some(page) = pages.next_page().await {
+			res.extend(page?);
 		}
 
 		Ok(res)
 	}
+
+	// Like `get_all_nbbo` but holds one page's (still compressed) body at a time instead of every
+	// row. Only the blocking client streams rows straight off the socket.
+	pub async fn get_all_nbbo_with(
+		&self,
+		symbol: &str,
+		date: NaiveDate,
+		on_row: impl FnMut(NBBO)
+	) -> Result<()> {
+		let params = NBBOsParams::new().limit(MAX_LIMIT).timestamp(date);
+		self
+			.paginate_nbbo(symbol, Some(&params))
+			.for_each_row(on_row)
+			.await
+	}
 }
 
 #[cfg(test)]
 mod nbbo {
 	use crate::{client::Client, equities::nbbo::NBBOsParams};
+	use chrono::NaiveDate;
 
 	#[test]
 	fn works() {
 		let client = Client::new().unwrap();
 		let limit = 500;
 		let params = NBBOsParams::new()
-			.timestamp("2005-01-03")
-			.limit(limit)
-			.params;
+			.timestamp(NaiveDate::from_ymd_opt(2005, 1, 3).unwrap())
+			.limit(limit);
 		let nbbo = client.get_nbbo("AAPL", Some(&params)).unwrap();
 		assert_eq!(nbbo.results.len(), limit);
 	}
@@ -143,7 +290,9 @@ mod nbbo {
 	#[test]
 	fn get_all_works() {
 		let client = Client::new().unwrap();
-		let trades = client.get_all_nbbo("AAPL", "2005-01-03").unwrap();
+		let trades = client
+			.get_all_nbbo("AAPL", NaiveDate::from_ymd_opt(2005, 1, 3).unwrap())
+			.unwrap();
 		let count = 58_819;
 		assert_eq!(trades.len(), count);
 	}
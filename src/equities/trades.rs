@@ -1,22 +1,30 @@
 extern crate serde_json;
 extern crate ureq;
+#[cfg(feature = "async")]
+use crate::{async_client::AsyncClient, paginator::AsyncPaginator};
 
 use crate::{
 	client::{Client, Error},
-	helpers::make_params,
-	with_param
+	decode::PageInfo,
+	helpers::{encode_segment, make_params, Order, Query},
+	paginator::Paginator,
+	range::{Range, RangeFilter, Timestamped},
+	time::{TimeConversion, Timestamp},
+	with_param, with_time_param
 };
-use serde::{de, Deserialize, Serialize, Serializer};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use serde::{de, ser, Deserialize, Serialize, Serializer};
 use serde_json::to_string;
-use std::{collections::HashMap, fmt, io};
+use std::fmt;
 
-const MAX_LIMIT: usize = 50_000;
+pub(crate) const MAX_LIMIT: usize = 50_000;
 
 // Trade ID:
 // Up to 8 char string in 2015
 // Gone in 2017
 // Back as u64 string in 2018
-fn to_id<'de, D>(deserializer: D) -> Result<u64, D::Error>
+pub(crate) fn to_id<'de, D>(deserializer: D) -> Result<u64, D::Error>
 where
 	D: de::Deserializer<'de>
 {
@@ -40,10 +48,10 @@ where
 				let res = u64::from_be_bytes(res);
 				Ok(res)
 			} else if v.len() <= 20 {
-				let res = v.parse::<u64>().expect("Parseable u64");
-				Ok(res)
+				v.parse::<u64>()
+					.map_err(|_| de::Error::custom(format!("bad trade id {}", v)))
 			} else {
-				Err(de::Error::custom(&format!("bad trade id {}", v)))
+				Err(de::Error::custom(format!("bad trade id {}", v)))
 			}
 		}
 	}
@@ -51,7 +59,7 @@ where
 }
 
 // v3 returns things like "size":2.216834e+06
-fn f64_to_u32<'de, D>(deserializer: D) -> Result<u32, D::Error>
+pub(crate) fn f64_to_u32<'de, D>(deserializer: D) -> Result<u32, D::Error>
 where
 	D: de::Deserializer<'de>
 {
@@ -87,8 +95,7 @@ where
 {
 	match to_string(value) {
 		Ok(v) => serializer.serialize_str(&v),
-		// TODO: how to map error?
-		Err(e) => panic!("{}", e)
+		Err(e) => Err(ser::Error::custom(e))
 	}
 }
 
@@ -101,11 +108,11 @@ pub struct Trade {
 	#[serde(default)]
 	pub ticker: String,
 	#[serde(rename(deserialize = "sip_timestamp"))]
-	pub time: i64,
+	pub time: Timestamp,
 	#[serde(rename(deserialize = "participant_timestamp"))]
-	pub time_participant: Option<i64>,
+	pub time_participant: Option<Timestamp>,
 	#[serde(rename(deserialize = "trf_timestamp"))]
-	pub time_trf: Option<i64>,
+	pub time_trf: Option<Timestamp>,
 	#[serde(default)]
 	pub price: f64,
 	#[serde(deserialize_with = "f64_to_u32", default)]
@@ -119,30 +126,38 @@ pub struct Trade {
 	pub trf: Option<u8>
 }
 
+impl Timestamped for Trade {
+	fn timestamp(&self) -> Timestamp { self.time }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TradesResponse {
-	pub results:  Vec<Trade>,
+	pub results: Vec<Trade>,
 	pub next_url: Option<String>,
-	pub status:   String, // For debugging
-	pub uri:      Option<String>
+	pub status: String, // For debugging
+	pub uri: Option<String>,
+	// Rows dropped by a lenient `RangeCheck`
+	#[serde(skip)]
+	pub out_of_range: usize
 }
 
-pub struct TradesParams<'a> {
-	pub params: HashMap<&'a str, String>
+#[derive(Clone, Debug, Default)]
+pub struct TradesParams {
+	pub(crate) params: Query
 }
 
-impl<'a> TradesParams<'a> {
-	with_param!(timestamp, &str);
+impl TradesParams {
+	with_time_param!(timestamp, "timestamp");
 
-	with_param!(timestamp_lt, &str);
+	with_time_param!(timestamp_lt, "timestamp.lt");
 
-	with_param!(timestamp_lte, &str);
+	with_time_param!(timestamp_lte, "timestamp.lte");
 
-	with_param!(timestamp_gt, &str);
+	with_time_param!(timestamp_gt, "timestamp.gt");
 
-	with_param!(timestamp_gte, &str);
+	with_time_param!(timestamp_gte, "timestamp.gte");
 
-	with_param!(order, &str);
+	with_param!(order, Order);
 
 	with_param!(reverse, bool);
 
@@ -151,10 +166,41 @@ impl<'a> TradesParams<'a> {
 	// Undocumented but appears in next_page_path
 	with_param!(cursor, &str);
 
-	pub fn new() -> Self {
-		Self {
-			params: HashMap::with_capacity(4)
-		}
+	pub fn new() -> Self { Self::default() }
+}
+
+impl AsRef<Query> for TradesParams {
+	fn as_ref(&self) -> &Query { &self.params }
+}
+
+fn trades_uri(api_uri: &str, symbol: &str, params: Option<&TradesParams>) -> String {
+	format!(
+		"{}/v3/trades/{}{}",
+		api_uri,
+		encode_segment(symbol),
+		make_params(params)
+	)
+}
+
+// Polygon reads dates in the symbol's exchange time
+fn trades_range(symbol: &str, params: Option<&TradesParams>) -> Option<Range> {
+	Range::for_params(
+		params.map(|p| &p.params),
+		TimeConversion::Exchange.zone(symbol)
+	)
+}
+
+fn fix_trade(row: &mut Trade, symbol: &str, zone: Tz) {
+	row.ticker = symbol.to_string();
+	row.time = row.time.with_zone(zone);
+	row.time_participant = row.time_participant.map(|t| t.with_zone(zone));
+	row.time_trf = row.time_trf.map(|t| t.with_zone(zone));
+}
+
+fn fix_trades(resp: &mut TradesResponse, symbol: &str, conversion: TimeConversion) {
+	let zone = conversion.zone(symbol);
+	for row in resp.results.iter_mut() {
+		fix_trade(row, symbol, zone);
 	}
 }
 
@@ -162,62 +208,301 @@ impl Client {
 	pub fn get_trades(
 		&self,
 		symbol: &str,
-		params: Option<&HashMap<&str, String>>
+		params: Option<&TradesParams>
 	) -> Result<TradesResponse, Error> {
-		let uri = format!(
-			"{}/v3/trades/{}{}",
-			self.api_uri,
-			symbol,
-			make_params(params),
-		);
+		let uri = trades_uri(&self.api_uri, symbol, params);
 
 		let mut resp = self.get_response::<TradesResponse>(&uri)?;
+		fix_trades(&mut resp, symbol, self.time_conversion);
+		let filter = RangeFilter::new(trades_range(symbol, params), self.range_check);
+		resp.out_of_range = filter.retain(&uri, &mut resp.results)?;
 		resp.uri = Some(uri);
 
-		for row in resp.results.iter_mut() {
-			row.ticker = symbol.to_string();
+		Ok(resp)
+	}
+
+	// Like `get_trades` but streams rows to `on_row` as they're parsed
+	pub fn get_trades_with(
+		&self,
+		symbol: &str,
+		params: Option<&TradesParams>,
+		mut on_row: impl FnMut(Trade)
+	) -> Result<PageInfo, Error> {
+		let uri = trades_uri(&self.api_uri, symbol, params);
+		let zone = self.time_conversion.zone(symbol);
+		let mut filter = RangeFilter::new(trades_range(symbol, params), self.range_check);
+
+		let mut page = self.get_rows(&uri, |mut row: Trade| {
+			fix_trade(&mut row, symbol, zone);
+			if filter.keep(&row) {
+				on_row(row);
+			}
+		})?;
+		page.out_of_range = filter.finish(&uri)?;
+
+		Ok(page)
+	}
+
+	pub fn paginate_trades(
+		&self,
+		symbol: &str,
+		params: Option<&TradesParams>
+	) -> Paginator<'_, Trade> {
+		let zone = self.time_conversion.zone(symbol);
+		let range = trades_range(symbol, params);
+		let symbol = symbol.to_string();
+		self
+			.paginate(trades_uri(&self.api_uri, &symbol, params))
+			.with_fix(move |row: &mut Trade| fix_trade(row, &symbol, zone))
+			.with_range(range, self.range_check)
+	}
+
+	pub fn get_all_trades(&self, symbol: &str, date: NaiveDate) -> Result<Vec<Trade>, Error> {
+		let params = TradesParams::new().limit(MAX_LIMIT).timestamp(date);
+		let mut res = Vec::<Trade>::new();
+		for page in self.paginate_trades(symbol, Some(&params)) {
+			res.extend(page?);
 		}
 
+		Ok(res)
+	}
+
+	// Like `get_all_trades` but never holds more than one row in memory
+	pub fn get_all_trades_with(
+		&self,
+		symbol: &str,
+		date: NaiveDate,
+		on_row: impl FnMut(Trade)
+	) -> Result<(), Error> {
+		let params = TradesParams::new().limit(MAX_LIMIT).timestamp(date);
+		self
+			.paginate_trades(symbol, Some(&params))
+			.for_each_row(on_row)
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_trades(
+		&self,
+		symbol: &str,
+		params: Option<&TradesParams>
+	) -> Result<TradesResponse, Error> {
+		let uri = trades_uri(&self.api_uri, symbol, params);
+
+		let mut resp = self.get_response::<TradesResponse>(&uri).await?;
+		fix_trades(&mut resp, symbol, self.time_conversion);
+		let filter = RangeFilter::new(trades_range(symbol, params), self.range_check);
+		resp.out_of_range = filter.retain(&uri, &mut resp.results)?;
+		resp.uri = Some(uri);
+
 		Ok(resp)
 	}
 
-	pub fn get_all_trades(&self, symbol: &str, date: &str) -> Result<Vec<Trade>, Error> {
-		let mut params = TradesParams::new().limit(MAX_LIMIT).timestamp(date);
-		let mut res = Vec::<Trade>::new();
-		loop {
-			let page = self.get_trades(symbol, Some(&params.params))?;
-			res.extend(page.results.into_iter());
-			match page.next_url {
-				Some(next_url) => {
-					let split = next_url.split("cursor=").collect::<Vec<&str>>();
-					if split.len() != 2 {
-						let msg = format!("no cursor in next_url {}", next_url);
-						let io_error = io::Error::new(io::ErrorKind::UnexpectedEof, msg);
-						return Err(Error::IoError(io_error));
-					}
-					let cursor = split[1];
-					params = TradesParams::new().cursor(cursor);
+	// Like `get_trades` but passes rows to `on_row` instead of collecting them
+	pub async fn get_trades_with(
+		&self,
+		symbol: &str,
+		params: Option<&TradesParams>,
+		mut on_row: impl FnMut(Trade)
+	) -> Result<PageInfo, Error> {
+		let uri = trades_uri(&self.api_uri, symbol, params);
+		let zone = self.time_conversion.zone(symbol);
+		let mut filter = RangeFilter::new(trades_range(symbol, params), self.range_check);
+
+		let mut page = self
+			.get_rows(&uri, |mut row: Trade| {
+				fix_trade(&mut row, symbol, zone);
+				if filter.keep(&row) {
+					on_row(row);
 				}
-				None => break
-			};
+			})
+			.await?;
+		page.out_of_range = filter.finish(&uri)?;
+
+		Ok(page)
+	}
+
+	pub fn paginate_trades(
+		&self,
+		symbol: &str,
+		params: Option<&TradesParams>
+	) -> AsyncPaginator<'_, Trade> {
+		let zone = self.time_conversion.zone(symbol);
+		let range = trades_range(symbol, params);
+		let symbol = symbol.to_string();
+		self
+			.paginate(trades_uri(&self.api_uri, &symbol, params))
+			.with_fix(move |row: &mut Trade| fix_trade(row, &symbol, zone))
+			.with_range(range, self.range_check)
+	}
+
+	pub async fn get_all_trades(&self, symbol: &str, date: NaiveDate) -> Result<Vec<Trade>, Error> {
+		let params = TradesParams::new().limit(MAX_LIMIT).timestamp(date);
+		let mut pages = self.paginate_trades(symbol, Some(&params));
+		let mut res = Vec::<Trade>::new();
+		while let Some(page) = pages.next_page().await {
+			res.extend(page?);
 		}
 
 		Ok(res)
 	}
+
+	// Like `get_all_trades` but holds one page's (still compressed) body at a time instead of every
+	// row. Only the blocking client streams rows straight off the socket.
+	pub async fn get_all_trades_with(
+		&self,
+		symbol: &str,
+		date: NaiveDate,
+		on_row: impl FnMut(Trade)
+	) -> Result<(), Error> {
+		let params = TradesParams::new().limit(MAX_LIMIT).timestamp(date);
+		self
+			.paginate_trades(symbol, Some(&params))
+			.for_each_row(on_row)
+			.await
+	}
 }
 
 #[cfg(test)]
 mod trades {
-	use crate::{client::Client, equities::trades::TradesParams};
-	use crate::equities::trades::MAX_LIMIT;
+	use crate::{
+		client::{Client, Error},
+		equities::trades::{TradesParams, MAX_LIMIT},
+		range::RangeCheck,
+		test_server::{StubResponse, StubServer},
+		time::TimeConversion
+	};
+	use chrono::{NaiveDate, TimeZone, Utc};
+
+	fn trade_page(id: &str) -> String {
+		format!(
+			"{{\"results\":[{{\"tape\":3,\"id\":\"{}\",\"sip_timestamp\":1577975400000000000,\"price\":\
+			 74.06,\"size\":100,\"exchange\":11}}],\"status\":\"OK\"}}",
+			id
+		)
+	}
+
+	#[test]
+	fn parses_ids() {
+		let server = StubServer::new(vec![
+			StubResponse::new(200, &trade_page("12345678901")),
+			StubResponse::new(200, &trade_page("AB")),
+		]);
+		let client = server.client();
+		let trades = client.get_trades("AAPL", None).unwrap();
+		assert_eq!(trades.results[0].id, 12345678901);
+		assert_eq!(trades.results[0].ticker, "AAPL");
+		let trades = client.get_trades("AAPL", None).unwrap();
+		assert_eq!(trades.results[0].id, u64::from_be_bytes(*b"AB\0\0\0\0\0\0"));
+	}
+
+	#[test]
+	fn get_all_with_streams_pages() {
+		let first = trade_page("100000001").replace(
+			"\"status\"",
+			"\"next_url\":\"https://api.polygon.io/v3/trades/AAPL?cursor=abc\",\"status\""
+		);
+		let server = StubServer::new(vec![
+			StubResponse::new(200, &first),
+			StubResponse::new(200, &trade_page("100000002")),
+		]);
+		let mut ids = Vec::new();
+		server
+			.client()
+			.get_all_trades_with(
+				"AAPL",
+				NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+				|trade| {
+					assert_eq!(trade.ticker, "AAPL");
+					ids.push(trade.id);
+				}
+			)
+			.unwrap();
+		assert_eq!(ids, vec![100000001, 100000002]);
+		assert!(server
+			.request(1)
+			.starts_with("GET /v3/trades/AAPL?cursor=abc "));
+	}
+
+	#[test]
+	fn malformed_id() {
+		for id in ["12345678x01", "123456789012345678901"] {
+			let server = StubServer::new(vec![StubResponse::new(200, &trade_page(id))]);
+			let err = server.client().get_trades("AAPL", None).unwrap_err();
+			match err {
+				Error::Decode { source, .. } => assert!(source.to_string().contains("bad trade id")),
+				e => panic!("bad error type {}", e)
+			};
+		}
+	}
+
+	#[test]
+	fn time_conversion() {
+		let server = StubServer::new(vec![StubResponse::new(200, &trade_page("12345678901"))]);
+		let trades = server.client().get_trades("AAPL", None).unwrap();
+		assert_eq!(trades.results[0].time.nanos(), 1577975400000000000);
+		assert_eq!(
+			trades.results[0].time.to_string(),
+			"2020-01-02 09:30:00.000000000 EST"
+		);
+
+		let client = Client::builder()
+			.key("test")
+			.api_uri(&server.uri)
+			.time_conversion(TimeConversion::Utc)
+			.build()
+			.unwrap();
+		let trades = client.get_trades("AAPL", None).unwrap();
+		assert_eq!(
+			trades.results[0].time.to_string(),
+			"2020-01-02 14:30:00.000000000 UTC"
+		);
+	}
+
+	#[test]
+	fn typed_bounds() {
+		let server = StubServer::new(vec![StubResponse::new(200, &trade_page("12345678901"))]);
+		let params = TradesParams::new()
+			.timestamp_gte(Utc.with_ymd_and_hms(2020, 1, 2, 14, 30, 0).unwrap())
+			.timestamp_lt(1577975400000000001);
+		let trades = server.client().get_trades("AAPL", Some(&params)).unwrap();
+		assert_eq!(trades.results.len(), 1);
+		let request = server.request(0);
+		assert!(request.contains("timestamp.gte=1577975400000000000"));
+		assert!(request.contains("timestamp.lt=1577975400000000001"));
+	}
+
+	#[test]
+	fn out_of_range() {
+		let server = StubServer::new(vec![StubResponse::new(200, &trade_page("12345678901"))]);
+		let params = TradesParams::new().timestamp(NaiveDate::from_ymd_opt(2020, 1, 3).unwrap());
+		let strict = Client::builder()
+			.key("test")
+			.api_uri(&server.uri)
+			.range_check(RangeCheck::Strict)
+			.build()
+			.unwrap();
+		match strict.get_trades("AAPL", Some(&params)) {
+			Err(Error::OutOfRange { rows, .. }) => assert_eq!(rows[0]["ticker"], "AAPL"),
+			_ => panic!("bad error type")
+		};
+
+		let client = server.client();
+		let trades = client.get_trades("AAPL", Some(&params)).unwrap();
+		assert_eq!(trades.results.len(), 0);
+		assert_eq!(trades.out_of_range, 1);
+		let params = TradesParams::new().timestamp(NaiveDate::from_ymd_opt(2020, 1, 2).unwrap());
+		let trades = client.get_trades("AAPL", Some(&params)).unwrap();
+		assert_eq!(trades.results.len(), 1);
+	}
 
 	#[test]
 	fn appl_2004_works() {
 		let client = Client::new().unwrap();
 		let params = TradesParams::new()
-			.timestamp("2004-01-02")
-			.limit(MAX_LIMIT)
-			.params;
+			.timestamp(NaiveDate::from_ymd_opt(2004, 1, 2).unwrap())
+			.limit(MAX_LIMIT);
 		let trades = client.get_trades("AAPL", Some(&params)).unwrap();
 		let count = 7_452;
 		assert_eq!(trades.results.len(), count);
@@ -229,8 +514,7 @@ mod trades {
 		let limit = 500;
 		let params = TradesParams::new()
 			.limit(limit)
-			.timestamp("2004-01-02")
-			.params;
+			.timestamp(NaiveDate::from_ymd_opt(2004, 1, 2).unwrap());
 		let trades = client.get_trades("AAPL", Some(&params)).unwrap();
 		assert_eq!(trades.results.len(), limit);
 	}
@@ -238,7 +522,9 @@ mod trades {
 	#[test]
 	fn get_all_works() {
 		let client = Client::new().unwrap();
-		let trades = client.get_all_trades("AAPL", "2020-01-02").unwrap();
+		let trades = client
+			.get_all_trades("AAPL", NaiveDate::from_ymd_opt(2020, 1, 2).unwrap())
+			.unwrap();
 		let count = 283_504;
 		assert_eq!(trades.len(), count);
 	}
@@ -0,0 +1,19 @@
+// Forex types shared by the REST endpoints and the socket
+use crate::time::{from_millis, Timestamp};
+use serde::{Deserialize, Serialize};
+
+// Sent over the socket as { ev, p, x, a, b, t }. REST responses are converted.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ForexQuote {
+	// Like USD/CNH over the socket and C:USDCNH from REST
+	#[serde(rename(deserialize = "p"))]
+	pub pair:     String,
+	#[serde(rename(deserialize = "x"))]
+	pub exchange: u8,
+	#[serde(rename(deserialize = "b"))]
+	pub bid:      f64,
+	#[serde(rename(deserialize = "a"))]
+	pub ask:      f64,
+	#[serde(rename(deserialize = "t"), deserialize_with = "from_millis")]
+	pub time:     Timestamp
+}
@@ -1,30 +1,170 @@
-use std::collections::HashMap;
+use percent_encoding::{utf8_percent_encode, AsciiSet, PercentEncode, NON_ALPHANUMERIC};
+use std::fmt;
 
-pub fn make_params(params: Option<&HashMap<&str, String>>) -> String {
-	if params.is_none() {
-		return String::new();
+// Everything but RFC 3986's unreserved characters, so `.gte` modifiers pass through
+const QUERY: &AsciiSet = &NON_ALPHANUMERIC
+	.remove(b'-')
+	.remove(b'.')
+	.remove(b'_')
+	.remove(b'~');
+
+// Path segments may also hold ':' as in crypto and forex pairs like X:BTCUSD
+const SEGMENT: &AsciiSet = &QUERY.remove(b':');
+
+// A symbol or other value to put in a URL's path
+pub fn encode_segment(segment: &str) -> PercentEncode<'_> { utf8_percent_encode(segment, SEGMENT) }
+
+// Query parameters. `set` replaces any values for its key while `append` adds another.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Query {
+	params: Vec<(&'static str, String)>
+}
+
+impl Query {
+	pub fn set(&mut self, key: &'static str, val: String) {
+		self.params.retain(|(k, _)| *k != key);
+		self.params.push((key, val));
+	}
+
+	pub fn append(&mut self, key: &'static str, val: String) { self.params.push((key, val)); }
+
+	// First value for `key`
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self
+			.params
+			.iter()
+			.find(|(k, _)| *k == key)
+			.map(|(_, v)| v.as_str())
+	}
+
+	pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+		self
+			.params
+			.iter()
+			.filter(move |(k, _)| *k == key)
+			.map(|(_, v)| v.as_str())
 	}
-	let params = params.unwrap();
-	let mut res = String::from("?");
+
+	pub fn is_empty(&self) -> bool { self.params.is_empty() }
+}
+
+impl AsRef<Query> for Query {
+	fn as_ref(&self) -> &Query { self }
+}
+
+// Percent-encoded and sorted by key so the same parameters always make the same URL. Repeated
+// keys keep the order they were appended in.
+pub fn make_params(params: Option<&impl AsRef<Query>>) -> String {
+	let query = match params {
+		Some(params) if !params.as_ref().is_empty() => params.as_ref(),
+		_ => return String::new()
+	};
+	let mut params = query.params.iter().collect::<Vec<_>>();
+	params.sort_by_key(|(key, _)| *key);
 	let kvs = params
 		.iter()
-		.map(|(key, val)| format!("{}={}", key.to_lowercase(), val))
+		.map(|(key, val)| {
+			format!(
+				"{}={}",
+				utf8_percent_encode(key, QUERY),
+				utf8_percent_encode(val, QUERY)
+			)
+		})
 		.collect::<Vec<String>>();
 
-	if kvs.len() == 0 {
-		return String::new();
-	}
+	format!("?{}", kvs.join("&"))
+}
 
-	res.push_str(&kvs.join("&"));
-	return res;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+	Asc,
+	Desc
 }
 
+impl fmt::Display for Order {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", format!("{:?}", self).to_lowercase())
+	}
+}
+
+// `$key` is for names that aren't identifiers like `type` or `ticker.gte`
 #[macro_export]
 macro_rules! with_param {
+	($param:ident, $key:literal, $_type: ty) => {
+		pub fn $param(mut self, $param: $_type) -> Self {
+			self.params.set($key, $param.to_string());
+			self
+		}
+	};
 	($param:ident, $_type: ty) => {
 		pub fn $param(mut self, $param: $_type) -> Self {
-			self.params.insert(stringify!($param), $param.to_string());
+			self.params.set(stringify!($param), $param.to_string());
 			self
 		}
 	};
 }
+
+// For dates and times, which are rendered with `QueryTime` instead of `to_string`
+#[macro_export]
+macro_rules! with_time_param {
+	($param:ident, $key:literal) => {
+		pub fn $param(mut self, $param: impl $crate::time::QueryTime) -> Self {
+			self.params.set($key, $param.nanos_param());
+			self
+		}
+	};
+}
+
+#[cfg(test)]
+mod helpers {
+	use super::{encode_segment, make_params, Query};
+
+	#[test]
+	fn stable_order() {
+		let mut a = Query::default();
+		a.set("limit", "10".to_string());
+		a.set("timestamp.gte", "2020-01-02".to_string());
+		a.set("cursor", "YQ==&b".to_string());
+		let mut b = Query::default();
+		b.set("cursor", "YQ==&b".to_string());
+		b.set("timestamp.gte", "2020-01-02".to_string());
+		b.set("limit", "5".to_string());
+		b.set("limit", "10".to_string());
+		assert_eq!(make_params(Some(&a)), make_params(Some(&b)));
+		assert_eq!(
+			make_params(Some(&a)),
+			"?cursor=YQ%3D%3D%26b&limit=10&timestamp.gte=2020-01-02"
+		);
+		assert_eq!(make_params(Some(&Query::default())), "");
+		assert_eq!(make_params(None::<&Query>), "");
+	}
+
+	#[test]
+	fn repeated_keys() {
+		let mut query = Query::default();
+		query.append("ticker", "MSFT".to_string());
+		query.set("limit", "1".to_string());
+		query.append("ticker", "AAPL".to_string());
+		assert_eq!(
+			make_params(Some(&query)),
+			"?limit=1&ticker=MSFT&ticker=AAPL"
+		);
+		assert_eq!(query.get("ticker"), Some("MSFT"));
+		assert_eq!(query.get_all("ticker").collect::<Vec<_>>(), vec![
+			"MSFT", "AAPL"
+		]);
+		query.set("ticker", "GOOG".to_string());
+		assert_eq!(make_params(Some(&query)), "?limit=1&ticker=GOOG");
+	}
+
+	#[test]
+	fn segments() {
+		assert_eq!(encode_segment("BRK.A").to_string(), "BRK.A");
+		assert_eq!(encode_segment("X:BTCUSD").to_string(), "X:BTCUSD");
+		assert_eq!(
+			encode_segment("DOESN'T EXIST").to_string(),
+			"DOESN%27T%20EXIST"
+		);
+		assert_eq!(encode_segment("a/b?c").to_string(), "a%2Fb%3Fc");
+	}
+}
@@ -1,6 +1,25 @@
+// Test modules are named after the file they test
+#![allow(clippy::module_inception)]
+
+#[cfg(feature = "async")]
+pub mod async_client;
 pub mod client;
 pub mod core;
+pub mod crypto;
+pub mod decode;
 pub mod equities;
+pub mod forex;
 pub mod helpers;
 pub mod marketstatus;
+pub mod paginator;
+pub mod range;
+pub mod ratelimit;
 pub mod reference;
+pub mod snapshot;
+#[cfg(feature = "stream")]
+pub mod stream;
+#[cfg(test)]
+mod test_server;
+#[cfg(test)]
+mod test_trade;
+pub mod time;
@@ -1,3 +1,5 @@
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
 use crate::client::{Client, Result};
 use serde::{Deserialize, Serialize};
 
@@ -32,6 +34,16 @@ impl Client {
 	}
 }
 
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_market_status_now(&self) -> Result<MarketStatusNowResponse> {
+		let uri = format!("{}/v1/marketstatus/now", self.api_uri);
+		let resp = self.get_response::<MarketStatusNowResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+}
+
 #[cfg(test)]
 mod market_status_now {
 	use crate::client::Client;
@@ -1,3 +1,5 @@
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
 use crate::client::{Client, Result};
 use serde::{Deserialize, Serialize};
 
@@ -20,6 +22,18 @@ impl Client {
 	}
 }
 
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_market_status_upcoming(&self) -> Result<Vec<MarketHolidayResponse>> {
+		let uri = format!("{}/v1/marketstatus/upcoming", self.api_uri);
+		let resp = self
+			.get_response::<Vec<MarketHolidayResponse>>(&uri)
+			.await?;
+
+		Ok(resp)
+	}
+}
+
 #[cfg(test)]
 mod market_status_upcoming {
 	use crate::client::Client;
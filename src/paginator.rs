@@ -0,0 +1,466 @@
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::{
+	client::{Client, Error, Result},
+	range::{Range, RangeCheck, RangeFilter, Timestamped}
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+type Fix<'a, T> = Box<dyn Fn(&mut T) + Send + Sync + 'a>;
+
+// Where a paginator resumes from after a crash. `save` is called with the URI of each page before
+// it's fetched, so a page is only skipped on resume once the caller has moved past it. It's called
+// with `None` once the last page is done.
+pub trait Checkpoint {
+	fn load(&mut self) -> Result<Option<String>>;
+	fn save(&mut self, next_uri: Option<&str>) -> Result<()>;
+}
+
+impl<C: Checkpoint + ?Sized> Checkpoint for &mut C {
+	fn load(&mut self) -> Result<Option<String>> { (**self).load() }
+
+	fn save(&mut self, next_uri: Option<&str>) -> Result<()> { (**self).save(next_uri) }
+}
+
+// Keeps the next URI in a file, which is removed once the download finishes
+pub struct FileCheckpoint {
+	path: PathBuf
+}
+
+impl FileCheckpoint {
+	pub fn new(path: impl Into<PathBuf>) -> Self { Self { path: path.into() } }
+}
+
+impl Checkpoint for FileCheckpoint {
+	fn load(&mut self) -> Result<Option<String>> {
+		match fs::read_to_string(&self.path) {
+			Ok(uri) if uri.trim().is_empty() => Ok(None),
+			Ok(uri) => Ok(Some(uri.trim().to_string())),
+			Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+			Err(e) => Err(Error::IoError(e))
+		}
+	}
+
+	fn save(&mut self, next_uri: Option<&str>) -> Result<()> {
+		match next_uri {
+			// Write then rename so a crash mid-write can't leave half a URI
+			Some(uri) => {
+				let tmp = self.path.with_extension("tmp");
+				fs::write(&tmp, uri)
+					.and_then(|_| fs::rename(&tmp, &self.path))
+					.map_err(Error::IoError)
+			}
+			None => match fs::remove_file(&self.path) {
+				Err(e) if e.kind() != ErrorKind::NotFound => Err(Error::IoError(e)),
+				_ => Ok(())
+			}
+		}
+	}
+}
+
+type BoxedCheckpoint<'a> = Box<dyn Checkpoint + Send + 'a>;
+
+type Keep<T> = fn(&mut RangeFilter, &T) -> bool;
+
+// Keeps the path and query of Polygon's next_url exactly but sends it to our `api_uri`, which may
+// be a proxy
+fn rebase(api_uri: &str, next_url: &str) -> String {
+	let path_start = next_url
+		.find("://")
+		.and_then(|i| next_url[i + 3..].find('/').map(|j| i + 3 + j));
+	match path_start {
+		Some(i) => format!("{}{}", api_uri, &next_url[i..]),
+		None => next_url.to_string()
+	}
+}
+
+// Follows `next_url` for any v3 list endpoint, yielding a page of `results` at a time.
+// Stops after the first error. `next_uri` is then the page that failed.
+pub struct Paginator<'a, T> {
+	client: &'a Client,
+	next_uri: Option<String>,
+	fix: Option<Fix<'a, T>>,
+	checkpoint: Option<BoxedCheckpoint<'a>>,
+	range: Option<Range>,
+	check: RangeCheck,
+	keep: Option<Keep<T>>,
+	out_of_range: usize,
+	failed: bool
+}
+
+impl<'a, T: DeserializeOwned> Paginator<'a, T> {
+	pub fn new(client: &'a Client, uri: String) -> Self {
+		Self {
+			client,
+			next_uri: Some(uri),
+			fix: None,
+			checkpoint: None,
+			range: None,
+			check: RangeCheck::Off,
+			keep: None,
+			out_of_range: 0,
+			failed: false
+		}
+	}
+
+	// Post-processing for each row, like filling in the symbol
+	pub fn with_fix(mut self, fix: impl Fn(&mut T) + Send + Sync + 'a) -> Self {
+		self.fix = Some(Box::new(fix));
+		self
+	}
+
+	// Checks each row against the queried range after `fix`
+	pub fn with_range(mut self, range: Option<Range>, check: RangeCheck) -> Self
+	where
+		T: Timestamped + Serialize
+	{
+		self.range = range;
+		self.check = check;
+		self.keep = Some(RangeFilter::keep::<T>);
+		self
+	}
+
+	// The page that will be fetched next, or `None` when done. Pass it to `starting_at` to resume.
+	pub fn next_uri(&self) -> Option<&str> { self.next_uri.as_deref() }
+
+	// Rows dropped so far by a lenient `RangeCheck`
+	pub fn out_of_range(&self) -> usize { self.out_of_range }
+
+	// Starts from a `next_uri` saved by an earlier run instead of the first page
+	pub fn starting_at(mut self, uri: &str) -> Self {
+		self.next_uri = Some(rebase(&self.client.api_uri, uri));
+		self
+	}
+
+	// Resumes from `checkpoint` if it has a saved URI and keeps it up to date
+	pub fn with_checkpoint(mut self, mut checkpoint: impl Checkpoint + Send + 'a) -> Result<Self> {
+		if let Some(uri) = checkpoint.load()? {
+			self = self.starting_at(&uri);
+		}
+		self.checkpoint = Some(Box::new(checkpoint));
+		Ok(self)
+	}
+
+	fn save(&mut self) -> Result<()> {
+		match &mut self.checkpoint {
+			Some(checkpoint) => checkpoint.save(self.next_uri.as_deref()),
+			None => Ok(())
+		}
+	}
+
+	// Passes the rows of `uri` to `on_row` and moves on to the next page
+	fn fetch(&mut self, uri: &str, mut on_row: impl FnMut(T)) -> Result<()> {
+		let mut filter = RangeFilter::new(self.range, self.check);
+		let (fix, keep) = (&self.fix, self.keep);
+		let page = self.client.get_rows(uri, |mut row: T| {
+			if let Some(fix) = fix {
+				fix(&mut row);
+			}
+			if keep.is_none_or(|keep| keep(&mut filter, &row)) {
+				on_row(row);
+			}
+		})?;
+		self.out_of_range += filter.finish(uri)?;
+		self.next_uri = page.next_url.map(|u| rebase(&self.client.api_uri, &u));
+		Ok(())
+	}
+
+	// Passes each row to `on_row` as it's parsed instead of collecting pages
+	pub fn for_each_row(&mut self, mut on_row: impl FnMut(T)) -> Result<()> {
+		self.save()?;
+		while let Some(uri) = self.next_uri.take() {
+			self
+				.fetch(&uri, &mut on_row)
+				.inspect_err(|_| self.next_uri = Some(uri.clone()))?;
+			self.save()?;
+		}
+
+		Ok(())
+	}
+
+	pub fn rows(self) -> impl Iterator<Item = Result<T>> + 'a
+	where
+		T: 'a
+	{
+		self.flat_map(|page| match page {
+			Ok(rows) => rows.into_iter().map(Ok).collect::<Vec<_>>(),
+			Err(e) => vec![Err(e)]
+		})
+	}
+}
+
+impl<T: DeserializeOwned> Iterator for Paginator<'_, T> {
+	type Item = Result<Vec<T>>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.failed {
+			return None;
+		}
+		// The caller is done with the previous page by now
+		if let Err(e) = self.save() {
+			self.failed = true;
+			return Some(Err(e));
+		}
+		let uri = self.next_uri.take()?;
+		let mut rows = Vec::new();
+		match self.fetch(&uri, |row| rows.push(row)) {
+			Ok(()) => Some(Ok(rows)),
+			Err(e) => {
+				self.next_uri = Some(uri);
+				self.failed = true;
+				Some(Err(e))
+			}
+		}
+	}
+}
+
+impl Client {
+	pub fn paginate<T: DeserializeOwned>(&self, uri: String) -> Paginator<'_, T> {
+		Paginator::new(self, uri)
+	}
+}
+
+// `Paginator` for `AsyncClient`. Call `next_page` until it returns `None`.
+#[cfg(feature = "async")]
+pub struct AsyncPaginator<'a, T> {
+	client: &'a AsyncClient,
+	next_uri: Option<String>,
+	fix: Option<Fix<'a, T>>,
+	checkpoint: Option<BoxedCheckpoint<'a>>,
+	range: Option<Range>,
+	check: RangeCheck,
+	keep: Option<Keep<T>>,
+	out_of_range: usize,
+	failed: bool
+}
+
+#[cfg(feature = "async")]
+impl<'a, T: DeserializeOwned> AsyncPaginator<'a, T> {
+	pub fn new(client: &'a AsyncClient, uri: String) -> Self {
+		Self {
+			client,
+			next_uri: Some(uri),
+			fix: None,
+			checkpoint: None,
+			range: None,
+			check: RangeCheck::Off,
+			keep: None,
+			out_of_range: 0,
+			failed: false
+		}
+	}
+
+	pub fn with_fix(mut self, fix: impl Fn(&mut T) + Send + Sync + 'a) -> Self {
+		self.fix = Some(Box::new(fix));
+		self
+	}
+
+	pub fn with_range(mut self, range: Option<Range>, check: RangeCheck) -> Self
+	where
+		T: Timestamped + Serialize
+	{
+		self.range = range;
+		self.check = check;
+		self.keep = Some(RangeFilter::keep::<T>);
+		self
+	}
+
+	pub fn next_uri(&self) -> Option<&str> { self.next_uri.as_deref() }
+
+	pub fn out_of_range(&self) -> usize { self.out_of_range }
+
+	pub fn starting_at(mut self, uri: &str) -> Self {
+		self.next_uri = Some(rebase(&self.client.api_uri, uri));
+		self
+	}
+
+	pub fn with_checkpoint(mut self, mut checkpoint: impl Checkpoint + Send + 'a) -> Result<Self> {
+		if let Some(uri) = checkpoint.load()? {
+			self = self.starting_at(&uri);
+		}
+		self.checkpoint = Some(Box::new(checkpoint));
+		Ok(self)
+	}
+
+	fn save(&mut self) -> Result<()> {
+		match &mut self.checkpoint {
+			Some(checkpoint) => checkpoint.save(self.next_uri.as_deref()),
+			None => Ok(())
+		}
+	}
+
+	async fn fetch(&mut self, uri: &str, mut on_row: impl FnMut(T)) -> Result<()> {
+		let mut filter = RangeFilter::new(self.range, self.check);
+		let (fix, keep) = (&self.fix, self.keep);
+		let page = self
+			.client
+			.get_rows(uri, |mut row: T| {
+				if let Some(fix) = fix {
+					fix(&mut row);
+				}
+				if keep.is_none_or(|keep| keep(&mut filter, &row)) {
+					on_row(row);
+				}
+			})
+			.await?;
+		self.out_of_range += filter.finish(uri)?;
+		self.next_uri = page.next_url.map(|u| rebase(&self.client.api_uri, &u));
+		Ok(())
+	}
+
+	pub async fn next_page(&mut self) -> Option<Result<Vec<T>>> {
+		if self.failed {
+			return None;
+		}
+		// The caller is done with the previous page by now
+		if let Err(e) = self.save() {
+			self.failed = true;
+			return Some(Err(e));
+		}
+		let uri = self.next_uri.take()?;
+		let mut rows = Vec::new();
+		match self.fetch(&uri, |row| rows.push(row)).await {
+			Ok(()) => Some(Ok(rows)),
+			Err(e) => {
+				self.next_uri = Some(uri);
+				self.failed = true;
+				Some(Err(e))
+			}
+		}
+	}
+
+	pub async fn for_each_row(&mut self, mut on_row: impl FnMut(T)) -> Result<()> {
+		self.save()?;
+		while let Some(uri) = self.next_uri.take() {
+			if let Err(e) = self.fetch(&uri, &mut on_row).await {
+				self.next_uri = Some(uri);
+				return Err(e);
+			}
+			self.save()?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub fn paginate<T: DeserializeOwned>(&self, uri: String) -> AsyncPaginator<'_, T> {
+		AsyncPaginator::new(self, uri)
+	}
+}
+
+#[cfg(test)]
+mod paginator {
+	use super::{rebase, Checkpoint, FileCheckpoint};
+	use crate::test_server::{StubResponse, StubServer};
+	use serde_json::Value;
+	use std::{env, process};
+
+	#[test]
+	fn rebases() {
+		assert_eq!(
+			rebase(
+				"http://127.0.0.1:80",
+				"https://api.polygon.io/v3/trades/AAPL?cursor=a%3D&timestamp=2020-01-02"
+			),
+			"http://127.0.0.1:80/v3/trades/AAPL?cursor=a%3D&timestamp=2020-01-02"
+		);
+	}
+
+	#[test]
+	fn follows_next_url() {
+		let server = StubServer::new(vec![
+			StubResponse::new(
+				200,
+				r#"{"results":[1,2],"next_url":"https://api.polygon.io/v3/x?b=2&cursor=YQ%3D%3D&a=1"}"#
+			),
+			StubResponse::new(200, r#"{"results":[3]}"#),
+		]);
+		let client = server.client();
+		let pages = client
+			.paginate::<u32>(format!("{}/v3/x?a=1", server.uri))
+			.collect::<Result<Vec<_>, _>>()
+			.unwrap();
+		assert_eq!(pages, vec![vec![1, 2], vec![3]]);
+		assert!(server
+			.request(1)
+			.starts_with("GET /v3/x?b=2&cursor=YQ%3D%3D&a=1 "));
+	}
+
+	#[test]
+	fn stops_after_error() {
+		let server = StubServer::new(vec![
+			StubResponse::new(
+				200,
+				r#"{"results":[1],"next_url":"https://api.polygon.io/v3/x?cursor=b"}"#
+			),
+			StubResponse::new(403, ""),
+		]);
+		let client = server.client();
+		let mut paginator = client.paginate::<Value>(format!("{}/v3/x", server.uri));
+		assert!(paginator.next().unwrap().is_ok());
+		assert!(paginator.next().unwrap().is_err());
+		assert_eq!(
+			paginator.next_uri(),
+			Some(&*format!("{}/v3/x?cursor=b", server.uri))
+		);
+		assert!(paginator.next().is_none());
+	}
+
+	#[test]
+	fn rows() {
+		let server = StubServer::new(vec![
+			StubResponse::new(
+				200,
+				r#"{"results":[1,2],"next_url":"https://api.polygon.io/v3/x?cursor=b"}"#
+			),
+			StubResponse::new(200, r#"{"results":[3]}"#),
+		]);
+		let client = server.client();
+		let rows = client
+			.paginate::<u32>(format!("{}/v3/x", server.uri))
+			.with_fix(|row| *row *= 10)
+			.rows()
+			.collect::<Result<Vec<_>, _>>()
+			.unwrap();
+		assert_eq!(rows, vec![10, 20, 30]);
+	}
+
+	#[test]
+	fn resumes_from_checkpoint() {
+		let path = env::temp_dir().join(format!("polygon_io_checkpoint_{}", process::id()));
+		let server = StubServer::new(vec![
+			StubResponse::new(
+				200,
+				r#"{"results":[1,2],"next_url":"https://api.polygon.io/v3/x?cursor=b"}"#
+			),
+			StubResponse::new(403, ""),
+		]);
+		let client = server.client();
+		let mut rows = Vec::<u32>::new();
+		let res = client
+			.paginate::<u32>(format!("{}/v3/x", server.uri))
+			.with_checkpoint(FileCheckpoint::new(&path))
+			.unwrap()
+			.for_each_row(|row| rows.push(row));
+		assert!(res.is_err());
+		assert_eq!(rows, vec![1, 2]);
+		let saved = FileCheckpoint::new(&path).load().unwrap();
+		assert_eq!(saved, Some(format!("{}/v3/x?cursor=b", server.uri)));
+
+		// A new run, possibly against another host, picks up at the failed page
+		let server = StubServer::new(vec![StubResponse::new(200, r#"{"results":[3]}"#)]);
+		let client = server.client();
+		client
+			.paginate::<u32>(format!("{}/v3/x", server.uri))
+			.with_checkpoint(FileCheckpoint::new(&path))
+			.unwrap()
+			.for_each_row(|row| rows.push(row))
+			.unwrap();
+		assert_eq!(rows, vec![1, 2, 3]);
+		assert!(server.request(0).starts_with("GET /v3/x?cursor=b "));
+		assert!(!path.exists());
+	}
+}
@@ -0,0 +1,282 @@
+use crate::{
+	client::{Error, Result},
+	helpers::Query,
+	time::Timestamp
+};
+use chrono::{DateTime, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use serde::Serialize;
+use serde_json::Value;
+
+// What to do with rows outside the queried range. Polygon has returned them before.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RangeCheck {
+	// Fail with `Error::OutOfRange` listing the offending rows
+	Strict,
+	// Drop them and count them in `out_of_range`. The default, so callers that never saw such
+	// rows don't start getting errors.
+	#[default]
+	Lenient,
+	// Return rows as Polygon sent them
+	Off
+}
+
+pub trait Timestamped {
+	fn timestamp(&self) -> Timestamp;
+}
+
+// Nanoseconds since the Unix epoch. `start` is inclusive and `end` exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+	pub start: i64,
+	pub end:   i64
+}
+
+// A date or an instant as accepted by Polygon's query parameters
+enum Bound {
+	Day(NaiveDate),
+	Instant(i64)
+}
+
+impl Bound {
+	// Dates, RFC 3339 times or integers in units of `scale` nanoseconds
+	fn parse(s: &str, scale: i64) -> Option<Self> {
+		if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+			return s
+				.parse::<i64>()
+				.ok()?
+				.checked_mul(scale)
+				.map(Bound::Instant);
+		}
+		if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+			return Some(Bound::Day(date));
+		}
+		DateTime::parse_from_rfc3339(s)
+			.ok()?
+			.timestamp_nanos_opt()
+			.map(Bound::Instant)
+	}
+
+	// First nanosecond of the bound
+	fn start(&self, zone: Tz) -> Option<i64> {
+		match self {
+			Bound::Day(date) => day_start(*date, zone),
+			Bound::Instant(nanos) => Some(*nanos)
+		}
+	}
+
+	// First nanosecond after the bound
+	fn end(&self, zone: Tz) -> Option<i64> {
+		match self {
+			Bound::Day(date) => day_start(date.succ_opt()?, zone),
+			Bound::Instant(nanos) => nanos.checked_add(1)
+		}
+	}
+}
+
+fn day_start(date: NaiveDate, zone: Tz) -> Option<i64> {
+	zone
+		.from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+		.earliest()?
+		.timestamp_nanos_opt()
+}
+
+impl Range {
+	pub fn contains(&self, ts: Timestamp) -> bool {
+		ts.nanos() >= self.start && ts.nanos() < self.end
+	}
+
+	// `from` through `to` for aggregates, which take dates or millisecond timestamps. Dates are
+	// whole days in `zone`. Bars may start up to `bar_nanos` before `from` since weeks, months and
+	// so on are aligned to calendar boundaries.
+	pub(crate) fn for_aggs(from: &str, to: &str, bar_nanos: i64, zone: Tz) -> Option<Self> {
+		let start = Bound::parse(from, 1_000_000)?.start(zone)?;
+		let end = Bound::parse(to, 1_000_000)?.end(zone)?;
+		Some(Self {
+			start: start.saturating_sub(bar_nanos),
+			end
+		})
+	}
+
+	// A single day in `zone`
+	pub(crate) fn for_date(date: NaiveDate, zone: Tz) -> Option<Self> {
+		let day = Bound::Day(date);
+		Some(Self {
+			start: day.start(zone)?,
+			end:   day.end(zone)?
+		})
+	}
+
+	// `timestamp` and `timestamp.{gt,gte,lt,lte}` for v3 endpoints, which take dates or nanosecond
+	// timestamps. `None` if there are no bounds.
+	pub(crate) fn for_params(params: Option<&Query>, zone: Tz) -> Option<Self> {
+		let params = params?;
+		let get = |key: &str| params.get(key).and_then(|v| Bound::parse(v, 1));
+		let mut res = Self {
+			start: i64::MIN,
+			end:   i64::MAX
+		};
+		let mut bounded = false;
+		let mut bound_start = |nanos: Option<i64>| {
+			if let Some(nanos) = nanos {
+				res.start = res.start.max(nanos);
+				bounded = true;
+			}
+		};
+		if let Some(b) = get("timestamp") {
+			bound_start(b.start(zone));
+		}
+		if let Some(b) = get("timestamp.gte") {
+			bound_start(b.start(zone));
+		}
+		if let Some(b) = get("timestamp.gt") {
+			bound_start(b.end(zone));
+		}
+		let mut bound_end = |nanos: Option<i64>| {
+			if let Some(nanos) = nanos {
+				res.end = res.end.min(nanos);
+				bounded = true;
+			}
+		};
+		if let Some(b) = get("timestamp") {
+			bound_end(b.end(zone));
+		}
+		if let Some(b) = get("timestamp.lte") {
+			bound_end(b.end(zone));
+		}
+		if let Some(b) = get("timestamp.lt") {
+			bound_end(b.start(zone));
+		}
+
+		if bounded {
+			Some(res)
+		} else {
+			None
+		}
+	}
+}
+
+// Checks rows as they're parsed
+pub(crate) struct RangeFilter {
+	range:   Option<Range>,
+	check:   RangeCheck,
+	bad:     Vec<Value>,
+	dropped: usize
+}
+
+impl RangeFilter {
+	pub(crate) fn new(range: Option<Range>, check: RangeCheck) -> Self {
+		Self {
+			range,
+			check,
+			bad: Vec::new(),
+			dropped: 0
+		}
+	}
+
+	// Whether to pass `row` on
+	pub(crate) fn keep<T: Timestamped + Serialize>(&mut self, row: &T) -> bool {
+		match self.range {
+			Some(range) if self.check != RangeCheck::Off && !range.contains(row.timestamp()) => {
+				self.dropped += 1;
+				if self.check == RangeCheck::Strict {
+					self.bad.push(serde_json::to_value(row).unwrap_or_default());
+				}
+				false
+			}
+			_ => true
+		}
+	}
+
+	pub(crate) fn retain<T: Timestamped + Serialize>(
+		mut self,
+		uri: &str,
+		rows: &mut Vec<T>
+	) -> Result<usize> {
+		rows.retain(|row| self.keep(row));
+		self.finish(uri)
+	}
+
+	// Number of rows dropped, or the offending rows if strict
+	pub(crate) fn finish(self, uri: &str) -> Result<usize> {
+		match self.range {
+			Some(range) if !self.bad.is_empty() => Err(Error::OutOfRange {
+				uri: uri.to_string(),
+				range,
+				rows: self.bad
+			}),
+			_ => Ok(self.dropped)
+		}
+	}
+}
+
+#[cfg(test)]
+mod range {
+	use super::{Range, RangeCheck, RangeFilter, Timestamped};
+	use crate::{client::Error, helpers::Query, time::Timestamp};
+	use chrono::NaiveDate;
+	use chrono_tz::{America::New_York, UTC};
+	use serde::Serialize;
+
+	#[derive(Serialize)]
+	struct Row(i64);
+
+	impl Timestamped for Row {
+		fn timestamp(&self) -> Timestamp { Timestamp::from_nanos(self.0, UTC) }
+	}
+
+	const DAY: i64 = 24 * 60 * 60 * 1_000_000_000;
+	// 2020-01-02T00:00:00Z
+	const JAN_2: i64 = 1_577_923_200_000_000_000;
+	const EST: i64 = 5 * 60 * 60 * 1_000_000_000;
+
+	#[test]
+	fn aggs() {
+		let range = Range::for_aggs("2020-01-02", "2020-01-03", 0, New_York).unwrap();
+		assert_eq!(range.start, JAN_2 + EST);
+		assert_eq!(range.end, JAN_2 + 2 * DAY + EST);
+		let range = Range::for_aggs("1577923200000", "2020-01-02", DAY, UTC).unwrap();
+		assert_eq!(range.start, JAN_2 - DAY);
+		assert_eq!(range.end, JAN_2 + DAY);
+		assert!(Range::for_aggs("2020-01-0", "2020-01-02", 0, UTC).is_none());
+	}
+
+	#[test]
+	fn params() {
+		let mut params = Query::default();
+		assert!(Range::for_params(Some(&params), UTC).is_none());
+		params.set("timestamp", "2020-01-02".to_string());
+		let range = Range::for_params(Some(&params), UTC).unwrap();
+		assert_eq!((range.start, range.end), (JAN_2, JAN_2 + DAY));
+		params.set("timestamp.gt", (JAN_2 + 10).to_string());
+		params.set("timestamp.lt", "2020-01-02T12:00:00Z".to_string());
+		let range = Range::for_params(Some(&params), UTC).unwrap();
+		assert_eq!((range.start, range.end), (JAN_2 + 11, JAN_2 + DAY / 2));
+	}
+
+	#[test]
+	fn filter() {
+		let range = Range::for_date(NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(), UTC);
+		let rows = || vec![Row(JAN_2 - 1), Row(JAN_2), Row(JAN_2 + DAY)];
+
+		let mut lenient = rows();
+		let dropped = RangeFilter::new(range, RangeCheck::Lenient)
+			.retain("uri", &mut lenient)
+			.unwrap();
+		assert_eq!(dropped, 2);
+		assert_eq!(lenient.len(), 1);
+
+		let mut off = rows();
+		RangeFilter::new(range, RangeCheck::Off)
+			.retain("uri", &mut off)
+			.unwrap();
+		assert_eq!(off.len(), 3);
+
+		match RangeFilter::new(range, RangeCheck::Strict).retain("uri", &mut rows()) {
+			Err(Error::OutOfRange { rows, .. }) => {
+				assert_eq!(rows, vec![JAN_2 - 1, JAN_2 + DAY]);
+			}
+			_ => panic!("bad error type")
+		}
+	}
+}
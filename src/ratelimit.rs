@@ -0,0 +1,89 @@
+use std::{
+	sync::{Arc, Mutex},
+	thread,
+	time::{Duration, Instant}
+};
+
+struct Bucket {
+	tokens: f64,
+	last:   Instant
+}
+
+// Token bucket shared by every clone of a client. Polygon quotas are per key, not per connection.
+#[derive(Clone)]
+pub struct RateLimiter {
+	per_sec: f64,
+	burst:   f64,
+	bucket:  Arc<Mutex<Bucket>>
+}
+
+impl RateLimiter {
+	pub fn new(requests_per_minute: u32, burst: u32) -> Self {
+		let burst = burst.max(1) as f64;
+		Self {
+			per_sec: requests_per_minute.max(1) as f64 / 60.0,
+			burst,
+			bucket: Arc::new(Mutex::new(Bucket {
+				tokens: burst,
+				last:   Instant::now()
+			}))
+		}
+	}
+
+	// Takes a token and returns how long to wait before using it. Tokens may go negative so that
+	// concurrent callers queue up behind each other instead of all waking at once.
+	pub fn reserve(&self) -> Duration {
+		let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+		let now = Instant::now();
+		let elapsed = now.duration_since(bucket.last).as_secs_f64();
+		bucket.tokens = (bucket.tokens + elapsed * self.per_sec).min(self.burst);
+		bucket.last = now;
+		bucket.tokens -= 1.0;
+
+		if bucket.tokens >= 0.0 {
+			Duration::ZERO
+		} else {
+			Duration::from_secs_f64(-bucket.tokens / self.per_sec)
+		}
+	}
+
+	pub fn wait(&self) {
+		let delay = self.reserve();
+		if !delay.is_zero() {
+			thread::sleep(delay);
+		}
+	}
+
+	#[cfg(feature = "async")]
+	pub async fn wait_async(&self) {
+		let delay = self.reserve();
+		if !delay.is_zero() {
+			tokio::time::sleep(delay).await;
+		}
+	}
+}
+
+#[cfg(test)]
+mod ratelimit {
+	use super::RateLimiter;
+	use std::time::Duration;
+
+	#[test]
+	fn burst() {
+		let limiter = RateLimiter::new(60, 2);
+		assert_eq!(limiter.reserve(), Duration::ZERO);
+		assert_eq!(limiter.reserve(), Duration::ZERO);
+		let delay = limiter.reserve();
+		assert!(delay > Duration::from_millis(900) && delay <= Duration::from_secs(1));
+		let delay = limiter.reserve();
+		assert!(delay > Duration::from_millis(1900) && delay <= Duration::from_secs(2));
+	}
+
+	#[test]
+	fn shared_across_clones() {
+		let limiter = RateLimiter::new(5, 1);
+		let clone = limiter.clone();
+		assert_eq!(limiter.reserve(), Duration::ZERO);
+		assert!(clone.reserve() > Duration::from_secs(11));
+	}
+}
@@ -0,0 +1,148 @@
+use super::exchanges::AssetClass;
+#[cfg(feature = "async")]
+use crate::{async_client::AsyncClient, paginator::AsyncPaginator};
+use crate::{
+	client::{Client, Result},
+	helpers::{make_params, Order, Query},
+	paginator::Paginator,
+	with_param
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UpdateRule {
+	pub updates_high_low:   bool,
+	pub updates_open_close: bool,
+	pub updates_volume:     bool
+}
+
+// How a print affects bars built from the consolidated tape and from its own exchange
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UpdateRules {
+	pub consolidated:  UpdateRule,
+	pub market_center: UpdateRule
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Condition {
+	// What `Trade.conditions` refers to
+	pub id: u32,
+	// "sale_condition", "quote_condition", "settlement_condition", ...
+	pub r#type: String,
+	pub name: String,
+	pub abbreviation: Option<String>,
+	pub description: Option<String>,
+	pub asset_class: String,
+	#[serde(default)]
+	pub data_types: Vec<String>,
+	#[serde(default)]
+	pub legacy: bool,
+	pub exchange: Option<u32>,
+	// Code used by each SIP, keyed by "CTA", "UTP" or "OPRA"
+	#[serde(default)]
+	pub sip_mapping: HashMap<String, String>,
+	// Missing for conditions that don't apply to trades
+	pub update_rules: Option<UpdateRules>
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ConditionsResponse {
+	#[serde(default)]
+	pub results:    Vec<Condition>,
+	pub next_url:   Option<String>,
+	// For debugging
+	pub status:     String,
+	pub request_id: String
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataType {
+	Trade,
+	BBO,
+	NBBO
+}
+
+impl fmt::Display for DataType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", format!("{:?}", self).to_lowercase())
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sip {
+	CTA,
+	UTP,
+	OPRA
+}
+
+impl fmt::Display for Sip {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{:?}", self) }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ConditionsParams {
+	pub(crate) params: Query
+}
+
+impl ConditionsParams {
+	with_param!(asset_class, AssetClass);
+
+	with_param!(data_type, DataType);
+
+	with_param!(id, u32);
+
+	with_param!(sip, Sip);
+
+	with_param!(order, Order);
+
+	// At most 1000
+	with_param!(limit, usize);
+
+	with_param!(sort, &str);
+
+	pub fn new() -> Self { Self::default() }
+}
+
+impl AsRef<Query> for ConditionsParams {
+	fn as_ref(&self) -> &Query { &self.params }
+}
+
+fn conditions_uri(api_uri: &str, params: Option<&ConditionsParams>) -> String {
+	format!("{}/v3/reference/conditions{}", api_uri, make_params(params))
+}
+
+impl Client {
+	pub fn get_conditions(&self, params: Option<&ConditionsParams>) -> Result<ConditionsResponse> {
+		let uri = conditions_uri(&self.api_uri, params);
+
+		let resp = self.get_response::<ConditionsResponse>(&uri)?;
+
+		Ok(resp)
+	}
+
+	pub fn paginate_conditions(&self, params: Option<&ConditionsParams>) -> Paginator<'_, Condition> {
+		self.paginate(conditions_uri(&self.api_uri, params))
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_conditions(
+		&self,
+		params: Option<&ConditionsParams>
+	) -> Result<ConditionsResponse> {
+		let uri = conditions_uri(&self.api_uri, params);
+
+		let resp = self.get_response::<ConditionsResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+
+	pub fn paginate_conditions(
+		&self,
+		params: Option<&ConditionsParams>
+	) -> AsyncPaginator<'_, Condition> {
+		self.paginate(conditions_uri(&self.api_uri, params))
+	}
+}
@@ -1,7 +1,12 @@
 extern crate serde_json;
 extern crate ureq;
 
-use crate::client::{Client, Result};
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::{
+	client::{Client, Result},
+	helpers::encode_segment
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -25,7 +30,11 @@ pub struct DividendsResponse {
 
 impl Client {
 	pub fn get_dividends(&self, symbol: &str) -> Result<DividendsResponse> {
-		let uri = format!("{}/v2/reference/dividends/{}", self.api_uri, symbol);
+		let uri = format!(
+			"{}/v2/reference/dividends/{}",
+			self.api_uri,
+			encode_segment(symbol)
+		);
 
 		let resp = self.get_response::<DividendsResponse>(&uri)?;
 
@@ -33,6 +42,21 @@ impl Client {
 	}
 }
 
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_dividends(&self, symbol: &str) -> Result<DividendsResponse> {
+		let uri = format!(
+			"{}/v2/reference/dividends/{}",
+			self.api_uri,
+			encode_segment(symbol)
+		);
+
+		let resp = self.get_response::<DividendsResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+}
+
 #[cfg(test)]
 mod dividends {
 	use crate::client::Client;
@@ -0,0 +1,91 @@
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::{
+	client::{Client, Result},
+	helpers::{make_params, Query},
+	with_param
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetClass {
+	Stocks,
+	Options,
+	Crypto,
+	FX
+}
+
+impl fmt::Display for AssetClass {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", format!("{:?}", self).to_lowercase())
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Exchange {
+	// What `Trade.exchange`, `NBBO.bid_exchange` and `NBBO.ask_exchange` refer to
+	pub id: u32,
+	// "exchange", "TRF" or "SIP"
+	pub r#type: String,
+	pub asset_class: String,
+	pub locale: String,
+	pub name: String,
+	pub acronym: Option<String>,
+	pub mic: Option<String>,
+	pub operating_mic: Option<String>,
+	pub participant_id: Option<String>,
+	pub url: Option<String>
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ExchangesResponse {
+	#[serde(default)]
+	pub results:    Vec<Exchange>,
+	// For debugging
+	pub status:     String,
+	pub request_id: String
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExchangesParams {
+	pub(crate) params: Query
+}
+
+impl ExchangesParams {
+	with_param!(asset_class, AssetClass);
+
+	// "us" or "global"
+	with_param!(locale, &str);
+
+	pub fn new() -> Self { Self::default() }
+}
+
+impl AsRef<Query> for ExchangesParams {
+	fn as_ref(&self) -> &Query { &self.params }
+}
+
+fn exchanges_uri(api_uri: &str, params: Option<&ExchangesParams>) -> String {
+	format!("{}/v3/reference/exchanges{}", api_uri, make_params(params))
+}
+
+impl Client {
+	pub fn get_exchanges(&self, params: Option<&ExchangesParams>) -> Result<ExchangesResponse> {
+		let uri = exchanges_uri(&self.api_uri, params);
+
+		let resp = self.get_response::<ExchangesResponse>(&uri)?;
+
+		Ok(resp)
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_exchanges(&self, params: Option<&ExchangesParams>) -> Result<ExchangesResponse> {
+		let uri = exchanges_uri(&self.api_uri, params);
+
+		let resp = self.get_response::<ExchangesResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+}
@@ -1,7 +1,12 @@
 extern crate serde_json;
 extern crate ureq;
 
-use crate::client::{Client, Result};
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::{
+	client::{Client, Result},
+	helpers::encode_segment
+};
 use serde::{Deserialize, Serialize};
 
 const DEFAULT_DATE: &str = "1970-01-01";
@@ -263,7 +268,11 @@ pub struct FinancialsResponse {
 
 impl Client {
 	pub fn get_financials(&self, symbol: &str) -> Result<FinancialsResponse> {
-		let uri = format!("{}/v2/reference/financials/{}", self.api_uri, symbol);
+		let uri = format!(
+			"{}/v2/reference/financials/{}",
+			self.api_uri,
+			encode_segment(symbol)
+		);
 
 		let resp = self.get_response::<FinancialsResponse>(&uri)?;
 
@@ -271,6 +280,21 @@ impl Client {
 	}
 }
 
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_financials(&self, symbol: &str) -> Result<FinancialsResponse> {
+		let uri = format!(
+			"{}/v2/reference/financials/{}",
+			self.api_uri,
+			encode_segment(symbol)
+		);
+
+		let resp = self.get_response::<FinancialsResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+}
+
 #[cfg(test)]
 mod financials {
 	use crate::client::Client;
@@ -1,6 +1,8 @@
 extern crate serde_json;
 extern crate ureq;
 
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
 use crate::client::{Client, Result};
 use serde::{Deserialize, Serialize};
 
@@ -28,6 +30,17 @@ impl Client {
 	}
 }
 
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_locales(&self) -> Result<LocalesResponse> {
+		let uri = format!("{}/v2/reference/locales", self.api_uri);
+
+		let resp = self.get_response::<LocalesResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+}
+
 #[cfg(test)]
 mod locales {
 	use crate::client::Client;
@@ -37,9 +50,6 @@ mod locales {
 		let client = Client::new().unwrap();
 		let locales = client.get_locales().unwrap();
 		assert!(locales.results.len() > 17);
-		assert!(locales
-			.results
-			.iter()
-			.any(|res| res.locale == String::from("US")));
+		assert!(locales.results.iter().any(|res| res.locale == "US"));
 	}
 }
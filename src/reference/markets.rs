@@ -1,6 +1,8 @@
 extern crate serde_json;
 extern crate ureq;
 
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
 use crate::client::{Client, Result};
 use serde::{Deserialize, Serialize};
 
@@ -28,6 +30,17 @@ impl Client {
 	}
 }
 
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_markets(&self) -> Result<MarketsResponse> {
+		let uri = format!("{}/v2/reference/markets", self.api_uri);
+
+		let resp = self.get_response::<MarketsResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+}
+
 #[cfg(test)]
 mod markets {
 	use crate::client::Client;
@@ -36,9 +49,6 @@ mod markets {
 	fn works() {
 		let client = Client::new().unwrap();
 		let markets = client.get_markets().unwrap();
-		assert!(markets
-			.results
-			.iter()
-			.any(|res| res.market == String::from("STOCKS")));
+		assert!(markets.results.iter().any(|res| res.market == "STOCKS"));
 	}
 }
@@ -1,7 +1,10 @@
+pub mod conditions;
 pub mod dividends;
+pub mod exchanges;
 pub mod financials;
 pub mod locales;
 pub mod markets;
+pub mod reference_data;
 pub mod splits;
 pub mod ticker_details;
 pub mod tickers;
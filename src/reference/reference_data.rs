@@ -0,0 +1,237 @@
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::{
+	client::{Client, Result},
+	helpers::Order,
+	reference::{
+		conditions::{Condition, ConditionsParams, DataType, UpdateRule},
+		exchanges::{AssetClass, Exchange, ExchangesParams}
+	}
+};
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex}
+};
+
+// Shared by clones of a client like the rate limiter
+pub(crate) type ReferenceCache = Arc<Mutex<Option<Arc<ReferenceData>>>>;
+
+// Only locked to read or fill, never across a fetch. A panic while holding it can't leave it
+// half-written, so poisoning is ignored.
+fn cached(cache: &ReferenceCache) -> Option<Arc<ReferenceData>> {
+	cache.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+// Keeps whichever of concurrent first fetches finished first so every caller shares it
+fn store(cache: &ReferenceCache, data: ReferenceData) -> Arc<ReferenceData> {
+	let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+	cache.get_or_insert_with(|| Arc::new(data)).clone()
+}
+
+// US stock exchanges and trade conditions by id
+#[derive(Clone, Debug, Default)]
+pub struct ReferenceData {
+	pub exchanges:  HashMap<u32, Exchange>,
+	pub conditions: HashMap<u32, Condition>
+}
+
+// Tape letter for `Trade.tape` and `NBBO.tape`: A is NYSE, B is NYSE Arca and other regionals
+// and C is Nasdaq
+pub fn tape_name(tape: u8) -> Option<&'static str> {
+	match tape {
+		1 => Some("A"),
+		2 => Some("B"),
+		3 => Some("C"),
+		_ => None
+	}
+}
+
+fn exchanges_params() -> ExchangesParams {
+	ExchangesParams::new()
+		.asset_class(AssetClass::Stocks)
+		.locale("us")
+}
+
+fn conditions_params() -> ConditionsParams {
+	ConditionsParams::new()
+		.asset_class(AssetClass::Stocks)
+		.data_type(DataType::Trade)
+		.order(Order::Asc)
+		.limit(1000)
+}
+
+impl ReferenceData {
+	pub fn new(exchanges: Vec<Exchange>, conditions: Vec<Condition>) -> Self {
+		Self {
+			exchanges:  exchanges.into_iter().map(|e| (e.id, e)).collect(),
+			conditions: conditions.into_iter().map(|c| (c.id, c)).collect()
+		}
+	}
+
+	pub fn fetch(client: &Client) -> Result<Self> {
+		let exchanges = client.get_exchanges(Some(&exchanges_params()))?.results;
+		let mut conditions = Vec::new();
+		for page in client.paginate_conditions(Some(&conditions_params())) {
+			conditions.extend(page?);
+		}
+
+		Ok(Self::new(exchanges, conditions))
+	}
+
+	#[cfg(feature = "async")]
+	pub async fn fetch_async(client: &AsyncClient) -> Result<Self> {
+		let exchanges = client
+			.get_exchanges(Some(&exchanges_params()))
+			.await?
+			.results;
+		let params = conditions_params();
+		let mut pages = client.paginate_conditions(Some(&params));
+		let mut conditions = Vec::new();
+		while let Some(page) = pages.next_page().await {
+			conditions.extend(page?);
+		}
+
+		Ok(Self::new(exchanges, conditions))
+	}
+
+	pub fn exchange(&self, id: impl Into<u32>) -> Option<&Exchange> { self.exchanges.get(&id.into()) }
+
+	pub fn mic(&self, id: impl Into<u32>) -> Option<&str> {
+		self.exchange(id).and_then(|e| e.mic.as_deref())
+	}
+
+	pub fn exchange_name(&self, id: impl Into<u32>) -> Option<&str> {
+		self.exchange(id).map(|e| e.name.as_str())
+	}
+
+	pub fn condition(&self, id: impl Into<u32>) -> Option<&Condition> {
+		self.conditions.get(&id.into())
+	}
+
+	// Consolidated rules. `None` for unknown conditions or ones without rules.
+	fn rule(&self, id: impl Into<u32>) -> Option<UpdateRule> {
+		self
+			.condition(id)
+			.and_then(|c| c.update_rules)
+			.map(|r| r.consolidated)
+	}
+
+	pub fn updates_high_low(&self, id: impl Into<u32>) -> Option<bool> {
+		self.rule(id).map(|r| r.updates_high_low)
+	}
+
+	pub fn updates_volume(&self, id: impl Into<u32>) -> Option<bool> {
+		self.rule(id).map(|r| r.updates_volume)
+	}
+
+	// Whether the print can set the last (and so open and close) price
+	pub fn eligible_for_last(&self, id: impl Into<u32>) -> Option<bool> {
+		self.rule(id).map(|r| r.updates_open_close)
+	}
+}
+
+impl Client {
+	// Fetched on first use and shared by clones of this client. Concurrent first calls may each
+	// fetch.
+	pub fn reference_data(&self) -> Result<Arc<ReferenceData>> {
+		if let Some(data) = cached(&self.reference) {
+			return Ok(data);
+		}
+		let data = ReferenceData::fetch(self)?;
+
+		Ok(store(&self.reference, data))
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	// Fetched on first use and shared by clones of this client. Concurrent first calls may each
+	// fetch.
+	pub async fn reference_data(&self) -> Result<Arc<ReferenceData>> {
+		if let Some(data) = cached(&self.reference) {
+			return Ok(data);
+		}
+		let data = ReferenceData::fetch_async(self).await?;
+
+		Ok(store(&self.reference, data))
+	}
+}
+
+#[cfg(test)]
+mod reference_data {
+	use super::tape_name;
+	use crate::test_server::{StubResponse, StubServer};
+	use std::thread;
+
+	const EXCHANGES: &str = r#"{"status":"OK","request_id":"x","count":2,"results":[
+		{"acronym":"AMEX","asset_class":"stocks","id":1,"locale":"us","mic":"XASE",
+		"name":"NYSE American, LLC","operating_mic":"XNYS","participant_id":"A","type":"exchange",
+		"url":"https://www.nyse.com/markets/nyse-american"},
+		{"asset_class":"stocks","id":4,"locale":"us","name":"FINRA Alternative Display Facility",
+		"operating_mic":"FINR","participant_id":"D","type":"TRF"}]}"#;
+
+	const CONDITIONS: &str = r#"{"status":"OK","request_id":"x","count":2,"results":[
+		{"asset_class":"stocks","data_types":["trade"],"id":2,"name":"Average Price Trade",
+		"sip_mapping":{"CTA":"B","UTP":"W"},"type":"sale_condition","update_rules":{
+		"consolidated":{"updates_high_low":false,"updates_open_close":false,"updates_volume":true},
+		"market_center":{"updates_high_low":false,"updates_open_close":false,"updates_volume":true}}},
+		{"asset_class":"stocks","data_types":["trade"],"id":37,"name":"Odd Lot Trade",
+		"sip_mapping":{"CTA":"I","UTP":"I"},"type":"sale_condition","update_rules":{
+		"consolidated":{"updates_high_low":false,"updates_open_close":false,"updates_volume":true},
+		"market_center":{"updates_high_low":false,"updates_open_close":false,"updates_volume":true}}}]}"#;
+
+	#[test]
+	fn lookups() {
+		let server = StubServer::new(vec![
+			StubResponse::new(200, EXCHANGES),
+			StubResponse::new(200, CONDITIONS),
+		]);
+		let client = server.client();
+		let data = client.reference_data().unwrap();
+		assert!(server
+			.request(0)
+			.starts_with("GET /v3/reference/exchanges?asset_class=stocks&locale=us "));
+		assert!(server.request(1).starts_with(
+			"GET /v3/reference/conditions?asset_class=stocks&data_type=trade&limit=1000&order=asc "
+		));
+
+		assert_eq!(data.mic(1u8), Some("XASE"));
+		assert_eq!(data.mic(4u8), None);
+		assert_eq!(
+			data.exchange_name(4u8),
+			Some("FINRA Alternative Display Facility")
+		);
+		assert_eq!(data.condition(37u8).unwrap().name, "Odd Lot Trade");
+		assert_eq!(data.updates_high_low(37u8), Some(false));
+		assert_eq!(data.updates_volume(2u8), Some(true));
+		assert_eq!(data.eligible_for_last(2u8), Some(false));
+		assert_eq!(data.eligible_for_last(99u8), None);
+		assert_eq!(tape_name(3), Some("C"));
+
+		// Cached, including across clones
+		client.clone().reference_data().unwrap();
+		assert_eq!(server.num_requests(), 2);
+	}
+
+	#[test]
+	fn poisoned() {
+		let server = StubServer::new(vec![
+			StubResponse::new(200, EXCHANGES),
+			StubResponse::new(200, CONDITIONS),
+		]);
+		let client = server.client();
+		let cache = client.reference.clone();
+		thread::spawn(move || {
+			let _guard = cache.lock().unwrap();
+			panic!("poison the cache");
+		})
+		.join()
+		.unwrap_err();
+		assert!(client.reference.is_poisoned());
+
+		let data = client.reference_data().unwrap();
+		assert_eq!(data.mic(1u8), Some("XASE"));
+		client.reference_data().unwrap();
+		assert_eq!(server.num_requests(), 2);
+	}
+}
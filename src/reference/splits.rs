@@ -1,7 +1,12 @@
 extern crate serde_json;
 extern crate ureq;
 
-use crate::client::{Client, Result};
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::{
+	client::{Client, Result},
+	helpers::encode_segment
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -29,7 +34,11 @@ pub struct SplitsResponse {
 
 impl Client {
 	pub fn get_splits(&self, symbol: &str) -> Result<SplitsResponse> {
-		let uri = format!("{}/v2/reference/splits/{}", self.api_uri, symbol);
+		let uri = format!(
+			"{}/v2/reference/splits/{}",
+			self.api_uri,
+			encode_segment(symbol)
+		);
 
 		let resp = self.get_response::<SplitsResponse>(&uri)?;
 
@@ -37,6 +46,21 @@ impl Client {
 	}
 }
 
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_splits(&self, symbol: &str) -> Result<SplitsResponse> {
+		let uri = format!(
+			"{}/v2/reference/splits/{}",
+			self.api_uri,
+			encode_segment(symbol)
+		);
+
+		let resp = self.get_response::<SplitsResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+}
+
 #[cfg(test)]
 mod splits {
 	use crate::client::Client;
@@ -1,13 +1,15 @@
 extern crate serde_json;
 extern crate ureq;
 
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
 use crate::{
 	client::{Client, Result},
 	helpers::*,
 	with_param
 };
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Address {
@@ -63,30 +65,31 @@ pub struct TickersResponse {
 	pub request_id: String
 }
 
-pub struct TickerDetailsParams<'a> {
-	pub params: HashMap<&'a str, String>
+#[derive(Clone, Debug, Default)]
+pub struct TickerDetailsParams {
+	pub(crate) params: Query
 }
 
-impl<'a> TickerDetailsParams<'a> {
-	with_param!(date, &str);
+impl TickerDetailsParams {
+	with_param!(date, NaiveDate);
 
-	pub fn new() -> Self {
-		Self {
-			params: HashMap::with_capacity(2)
-		}
-	}
+	pub fn new() -> Self { Self::default() }
+}
+
+impl AsRef<Query> for TickerDetailsParams {
+	fn as_ref(&self) -> &Query { &self.params }
 }
 
 impl Client {
 	pub fn get_ticker_details(
 		&self,
 		ticker: &str,
-		params: Option<&HashMap<&str, String>>
+		params: Option<&TickerDetailsParams>
 	) -> Result<TickersResponse> {
 		let uri = format!(
 			"{}/v3/reference/tickers/{}{}",
 			self.api_uri,
-			ticker,
+			encode_segment(ticker),
 			make_params(params),
 		);
 
@@ -96,12 +99,33 @@ impl Client {
 	}
 }
 
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_ticker_details(
+		&self,
+		ticker: &str,
+		params: Option<&TickerDetailsParams>
+	) -> Result<TickersResponse> {
+		let uri = format!(
+			"{}/v3/reference/tickers/{}{}",
+			self.api_uri,
+			encode_segment(ticker),
+			make_params(params),
+		);
+
+		let resp = self.get_response::<TickersResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+}
+
 #[cfg(test)]
 mod tickers {
 	use crate::{
 		client::{Client, Error},
 		reference::ticker_details::TickerDetailsParams
 	};
+	use chrono::NaiveDate;
 
 	#[test]
 	fn works() {
@@ -113,7 +137,7 @@ mod tickers {
 	#[test]
 	fn works_day() {
 		let client = Client::new().unwrap();
-		let params = TickerDetailsParams::new().date("2004-01-02").params;
+		let params = TickerDetailsParams::new().date(NaiveDate::from_ymd_opt(2004, 1, 2).unwrap());
 		let resp = client.get_ticker_details("AAPL", Some(&params)).unwrap();
 		assert_eq!(resp.results.market, "stocks");
 	}
@@ -121,7 +145,7 @@ mod tickers {
 	#[test]
 	fn works_empty() {
 		let client = Client::new().unwrap();
-		let params = TickerDetailsParams::new().date("2004-01-02").params;
+		let params = TickerDetailsParams::new().date(NaiveDate::from_ymd_opt(2004, 1, 2).unwrap());
 		let resp = client
 			.get_ticker_details("DOESN'T EXIST", Some(&params))
 			.unwrap_err();
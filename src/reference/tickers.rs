@@ -1,13 +1,17 @@
 extern crate serde_json;
 extern crate ureq;
 
+#[cfg(feature = "async")]
+use crate::{async_client::AsyncClient, paginator::AsyncPaginator};
 use crate::{
-	client::{Client, Error, Result},
+	client::{Client, Result},
 	helpers::*,
+	paginator::Paginator,
 	with_param
 };
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, io};
+use std::fmt;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Ticker {
@@ -36,80 +40,197 @@ pub struct TickersResponse {
 	pub request_id: String
 }
 
-pub struct TickersParams<'a> {
-	pub params: HashMap<&'a str, String>
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickersMarket {
+	Stocks,
+	Crypto,
+	FX,
+	OTC,
+	Indices
 }
 
-impl<'a> TickersParams<'a> {
+impl fmt::Display for TickersMarket {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", format!("{:?}", self).to_lowercase())
+	}
+}
+
+// Codes from /v3/reference/tickers/types
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickerType {
+	CS,
+	ADRC,
+	ADRP,
+	ADRR,
+	ADRW,
+	UNIT,
+	RIGHT,
+	PFD,
+	FUND,
+	SP,
+	WARRANT,
+	INDEX,
+	ETF,
+	ETN,
+	ETV,
+	ETS,
+	OS,
+	GDR,
+	OTHER,
+	NYRS,
+	AGEN,
+	EQLK,
+	BOND,
+	BASKET,
+	LT
+}
+
+impl fmt::Display for TickerType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{:?}", self) }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickersSort {
+	Ticker,
+	Name,
+	Market,
+	Locale,
+	PrimaryExchange,
+	Type,
+	CurrencySymbol,
+	CurrencyName,
+	BaseCurrencySymbol,
+	BaseCurrencyName,
+	Cik,
+	CompositeFigi,
+	ShareClassFigi,
+	LastUpdatedUtc,
+	DelistedUtc
+}
+
+impl fmt::Display for TickersSort {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match self {
+			TickersSort::Ticker => "ticker",
+			TickersSort::Name => "name",
+			TickersSort::Market => "market",
+			TickersSort::Locale => "locale",
+			TickersSort::PrimaryExchange => "primary_exchange",
+			TickersSort::Type => "type",
+			TickersSort::CurrencySymbol => "currency_symbol",
+			TickersSort::CurrencyName => "currency_name",
+			TickersSort::BaseCurrencySymbol => "base_currency_symbol",
+			TickersSort::BaseCurrencyName => "base_currency_name",
+			TickersSort::Cik => "cik",
+			TickersSort::CompositeFigi => "composite_figi",
+			TickersSort::ShareClassFigi => "share_class_figi",
+			TickersSort::LastUpdatedUtc => "last_updated_utc",
+			TickersSort::DelistedUtc => "delisted_utc"
+		})
+	}
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TickersParams {
+	pub(crate) params: Query
+}
+
+impl TickersParams {
 	with_param!(ticker, &str);
 
-	with_param!(r#type, &str);
+	with_param!(ticker_lt, "ticker.lt", &str);
+
+	with_param!(ticker_lte, "ticker.lte", &str);
+
+	with_param!(ticker_gt, "ticker.gt", &str);
+
+	with_param!(ticker_gte, "ticker.gte", &str);
+
+	with_param!(r#type, "type", TickerType);
 
-	with_param!(market, &str);
+	with_param!(market, TickersMarket);
 
 	with_param!(exchange, &str);
 
 	with_param!(cusip, &str);
 
-	with_param!(date, &str);
+	with_param!(date, NaiveDate);
 
 	with_param!(active, bool);
 
-	with_param!(sort, &str);
+	with_param!(sort, TickersSort);
 
-	with_param!(order, &str);
+	with_param!(order, Order);
 
 	with_param!(limit, usize);
 
 	// Undocumented but appears in next_page_path
 	with_param!(cursor, &str);
 
-	pub fn new() -> Self {
-		Self {
-			params: HashMap::with_capacity(8)
-		}
-	}
+	pub fn new() -> Self { Self::default() }
+}
+
+impl AsRef<Query> for TickersParams {
+	fn as_ref(&self) -> &Query { &self.params }
+}
+
+fn tickers_uri(api_uri: &str, params: Option<&TickersParams>) -> String {
+	format!("{}/v3/reference/tickers{}", api_uri, make_params(params))
+}
+
+fn all_tickers_params(date: NaiveDate) -> TickersParams {
+	TickersParams::new()
+		.market(TickersMarket::Stocks)
+		.limit(1000)
+		.order(Order::Asc)
+		.sort(TickersSort::Ticker)
+		.date(date)
 }
 
 impl Client {
-	pub fn get_tickers(&self, params: Option<&HashMap<&str, String>>) -> Result<TickersResponse> {
-		let uri = format!(
-			"{}/v3/reference/tickers{}",
-			self.api_uri,
-			make_params(params),
-		);
+	pub fn get_tickers(&self, params: Option<&TickersParams>) -> Result<TickersResponse> {
+		let uri = tickers_uri(&self.api_uri, params);
 
 		let resp = self.get_response::<TickersResponse>(&uri)?;
 
 		Ok(resp)
 	}
 
-	pub fn get_all_tickers(&self, date: &str) -> Result<Vec<Ticker>> {
-		let limit: usize = 1000;
-		// Use default params since next_page_path does as well
-		let mut params = TickersParams::new()
-			.market("stocks")
-			.limit(limit)
-			.order("asc")
-			.sort("ticker")
-			.date(date);
+	pub fn paginate_tickers(&self, params: Option<&TickersParams>) -> Paginator<'_, Ticker> {
+		self.paginate(tickers_uri(&self.api_uri, params))
+	}
+
+	pub fn get_all_tickers(&self, date: NaiveDate) -> Result<Vec<Ticker>> {
+		let params = all_tickers_params(date);
 		let mut res = Vec::<Ticker>::new();
-		loop {
-			let page = self.get_tickers(Some(&params.params))?;
-			res.extend(page.results.into_iter());
-			match page.next_url {
-				Some(next_url) => {
-					let split = next_url.split("cursor=").collect::<Vec<&str>>();
-					if split.len() != 2 {
-						let msg = format!("no cursor in next_url {}", next_url);
-						let io_error = io::Error::new(io::ErrorKind::UnexpectedEof, msg);
-						return Err(Error::IoError(io_error));
-					}
-					let cursor = split[1];
-					params = TickersParams::new().cursor(cursor);
-				}
-				None => break
-			};
+		for page in self.paginate_tickers(Some(&params)) {
+			res.extend(page?);
+		}
+
+		Ok(res)
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_tickers(&self, params: Option<&TickersParams>) -> Result<TickersResponse> {
+		let uri = tickers_uri(&self.api_uri, params);
+
+		let resp = self.get_response::<TickersResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+
+	pub fn paginate_tickers(&self, params: Option<&TickersParams>) -> AsyncPaginator<'_, Ticker> {
+		self.paginate(tickers_uri(&self.api_uri, params))
+	}
+
+	pub async fn get_all_tickers(&self, date: NaiveDate) -> Result<Vec<Ticker>> {
+		let params = all_tickers_params(date);
+		let mut pages = self.paginate_tickers(Some(&params));
+		let mut res = Vec::<Ticker>::new();
+		while let Some(page) = pages.next_page().await {
+			res.extend(page?);
 		}
 
 		Ok(res)
@@ -118,7 +239,30 @@ impl Client {
 
 #[cfg(test)]
 mod tickers {
-	use crate::client::Client;
+	use super::{TickerType, TickersMarket, TickersParams, TickersSort};
+	use crate::{
+		client::Client,
+		helpers::{make_params, Order}
+	};
+	use chrono::NaiveDate;
+
+	#[test]
+	fn params() {
+		let params = TickersParams::new()
+			.r#type(TickerType::CS)
+			.market(TickersMarket::FX)
+			.sort(TickersSort::PrimaryExchange)
+			.order(Order::Desc)
+			.cursor("a=b&c d")
+			.market(TickersMarket::Stocks)
+			.ticker_gte("BRK.A");
+		assert_eq!(
+			make_params(Some(&params)),
+			"?cursor=a%3Db%26c%20d&market=stocks&order=desc&sort=primary_exchange&ticker.gte=BRK.A&\
+			 type=CS"
+		);
+		assert_eq!(make_params(Some(&TickersParams::new())), "");
+	}
 
 	#[test]
 	fn works() {
@@ -130,7 +274,9 @@ mod tickers {
 	#[test]
 	fn works_day() {
 		let client = Client::new().unwrap();
-		let results = client.get_all_tickers("2004-01-02").unwrap();
+		let results = client
+			.get_all_tickers(NaiveDate::from_ymd_opt(2004, 1, 2).unwrap())
+			.unwrap();
 		assert_eq!(results.len(), 8163);
 	}
 }
@@ -1,6 +1,8 @@
 extern crate serde_json;
 extern crate ureq;
 
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
 use crate::client::{Client, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -29,6 +31,17 @@ impl Client {
 	}
 }
 
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_types(&self) -> Result<TypesResponse> {
+		let uri = format!("{}/v2/reference/types", self.api_uri);
+
+		let resp = self.get_response::<TypesResponse>(&uri).await?;
+
+		Ok(resp)
+	}
+}
+
 #[cfg(test)]
 mod types {
 	use crate::client::Client;
@@ -0,0 +1,593 @@
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::{
+	client::{Client, Result},
+	core::{default_num_ticks, Candle},
+	equities::trades::f64_to_u32,
+	helpers::{encode_segment, make_params, Query},
+	time::{TimeConversion, Timestamp},
+	with_param
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotMarket {
+	Stocks,
+	Forex,
+	Crypto
+}
+
+impl SnapshotMarket {
+	fn path(&self) -> &'static str {
+		match self {
+			SnapshotMarket::Stocks => "locale/us/markets/stocks",
+			SnapshotMarket::Forex => "locale/global/markets/forex",
+			SnapshotMarket::Crypto => "locale/global/markets/crypto"
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+	Gainers,
+	Losers
+}
+
+impl fmt::Display for Direction {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", format!("{:?}", self).to_lowercase())
+	}
+}
+
+// { o, h, l, c, v, vw } for day and prevDay, plus { t, n, av } for min
+#[derive(Debug, Deserialize)]
+struct WireBar {
+	// Milliseconds. Only sent for min.
+	#[serde(rename = "t")]
+	ts:        Option<i64>,
+	#[serde(rename = "o", default)]
+	open:      f64,
+	#[serde(rename = "h", default)]
+	high:      f64,
+	#[serde(rename = "l", default)]
+	low:       f64,
+	#[serde(rename = "c", default)]
+	close:     f64,
+	// Fractional for crypto
+	#[serde(rename = "v", default)]
+	volume:    f64,
+	#[serde(rename = "vw")]
+	vwap:      Option<f32>,
+	#[serde(rename = "n")]
+	num_ticks: Option<u64>
+}
+
+// A `Candle` without `ts`, since Polygon doesn't say when `day` and `prev_day` start
+#[derive(Debug, Serialize)]
+pub struct SnapshotBar {
+	pub open:   f64,
+	pub high:   f64,
+	pub low:    f64,
+	pub close:  f64,
+	// Fractional for crypto
+	pub volume: f64,
+	// NaN when missing
+	pub vwap:   f32
+}
+
+impl WireBar {
+	fn into_bar(self) -> SnapshotBar {
+		SnapshotBar {
+			open:   self.open,
+			high:   self.high,
+			low:    self.low,
+			close:  self.close,
+			volume: self.volume,
+			vwap:   self.vwap.unwrap_or(f32::NAN)
+		}
+	}
+
+	// `None` without a start
+	fn into_candle(self, symbol: &str, zone: Tz) -> Option<Candle> {
+		Some(Candle {
+			ts:        Timestamp::from_millis(self.ts?, zone),
+			symbol:    symbol.to_string(),
+			open:      self.open,
+			high:      self.high,
+			low:       self.low,
+			close:     self.close,
+			volume:    self.volume,
+			vwap:      self.vwap.unwrap_or(f32::NAN),
+			num_ticks: self.num_ticks.unwrap_or_else(default_num_ticks)
+		})
+	}
+}
+
+// { c, i, p, s, t, x }. `t` is nanoseconds for stocks and milliseconds for crypto.
+#[derive(Debug, Deserialize)]
+struct WireTrade {
+	#[serde(rename = "c", default)]
+	conditions: Vec<u8>,
+	#[serde(rename = "i", default)]
+	id:         String,
+	#[serde(rename = "p")]
+	price:      f64,
+	#[serde(rename = "s", default)]
+	size:       f64,
+	#[serde(rename = "t")]
+	time:       i64,
+	#[serde(rename = "x", default)]
+	exchange:   u8
+}
+
+// The latest trade in any market. Not a `Trade`, whose ids are numeric and sizes whole shares,
+// or a `CryptoTrade`, which stocks and forex would have to fill with crypto's fields.
+#[derive(Debug, Serialize)]
+pub struct SnapshotTrade {
+	pub time:       Timestamp,
+	// Crypto exchanges' own, not always numeric
+	pub id:         String,
+	pub price:      f64,
+	// Fractional for crypto
+	pub size:       f64,
+	pub exchange:   u8,
+	pub conditions: Vec<u8>
+}
+
+// Stocks send { P, S, p, s, t } in nanoseconds and forex { a, b, t, x } in milliseconds
+#[derive(Debug, Deserialize)]
+struct WireQuote {
+	#[serde(rename = "P", alias = "a")]
+	ask_price: f64,
+	#[serde(rename = "S", deserialize_with = "lots", default)]
+	ask_lots:  Option<u32>,
+	#[serde(rename = "p", alias = "b")]
+	bid_price: f64,
+	#[serde(rename = "s", deserialize_with = "lots", default)]
+	bid_lots:  Option<u32>,
+	#[serde(rename = "t")]
+	ts:        i64,
+	#[serde(rename = "x")]
+	exchange:  Option<u32>
+}
+
+fn lots<'de, D>(deserializer: D) -> std::result::Result<Option<u32>, D::Error>
+where
+	D: serde::Deserializer<'de>
+{
+	f64_to_u32(deserializer).map(Some)
+}
+
+// Not an `NBBO`, since stocks send sizes but no exchange and forex an exchange but no sizes
+#[derive(Debug, Serialize)]
+pub struct SnapshotQuote {
+	pub ts:        Timestamp,
+	pub bid_price: f64,
+	pub ask_price: f64,
+	pub bid_lots:  Option<u32>,
+	pub ask_lots:  Option<u32>,
+	pub exchange:  Option<u32>
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WireSnapshot {
+	ticker: String,
+	#[serde(default)]
+	todays_change: f64,
+	#[serde(default)]
+	todays_change_perc: f64,
+	// Nanoseconds
+	#[serde(default)]
+	updated: i64,
+	day: Option<WireBar>,
+	prev_day: Option<WireBar>,
+	min: Option<WireBar>,
+	last_trade: Option<WireTrade>,
+	last_quote: Option<WireQuote>
+}
+
+#[derive(Debug, Serialize)]
+pub struct Snapshot {
+	pub symbol: String,
+	pub todays_change: f64,
+	pub todays_change_perc: f64,
+	pub updated: Timestamp,
+	pub day: Option<SnapshotBar>,
+	pub prev_day: Option<SnapshotBar>,
+	// Latest minute bar
+	pub min: Option<Candle>,
+	pub last_trade: Option<SnapshotTrade>,
+	pub last_quote: Option<SnapshotQuote>
+}
+
+impl WireSnapshot {
+	fn into_snapshot(self, market: SnapshotMarket, conversion: TimeConversion) -> Snapshot {
+		let symbol = self.ticker;
+		let zone = conversion.zone(&symbol);
+		// Stocks send nanoseconds for the last trade and quote, crypto and forex milliseconds
+		let scale = match market {
+			SnapshotMarket::Stocks => 1,
+			_ => 1_000_000
+		};
+
+		Snapshot {
+			todays_change: self.todays_change,
+			todays_change_perc: self.todays_change_perc,
+			updated: Timestamp::from_nanos(self.updated, zone),
+			day: self.day.map(WireBar::into_bar),
+			prev_day: self.prev_day.map(WireBar::into_bar),
+			min: self.min.and_then(|b| b.into_candle(&symbol, zone)),
+			last_trade: self.last_trade.map(|t| SnapshotTrade {
+				time:       Timestamp::from_nanos(t.time * scale, zone),
+				id:         t.id,
+				price:      t.price,
+				size:       t.size,
+				exchange:   t.exchange,
+				conditions: t.conditions
+			}),
+			last_quote: self.last_quote.map(|q| SnapshotQuote {
+				ts:        Timestamp::from_nanos(q.ts * scale, zone),
+				bid_price: q.bid_price,
+				ask_price: q.ask_price,
+				bid_lots:  q.bid_lots,
+				ask_lots:  q.ask_lots,
+				exchange:  q.exchange
+			}),
+			symbol
+		}
+	}
+}
+
+#[derive(Debug, Deserialize)]
+struct WireSnapshotsResponse {
+	#[serde(default)]
+	tickers: Vec<WireSnapshot>,
+	status:  String
+}
+
+#[derive(Debug, Deserialize)]
+struct WireSnapshotResponse {
+	ticker: WireSnapshot,
+	status: String
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotsResponse {
+	pub tickers: Vec<Snapshot>,
+	// For debugging
+	pub status:  String,
+	pub uri:     Option<String>
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotResponse {
+	pub ticker: Snapshot,
+	// For debugging
+	pub status: String,
+	pub uri:    Option<String>
+}
+
+impl WireSnapshotsResponse {
+	fn fix(
+		self,
+		market: SnapshotMarket,
+		uri: String,
+		conversion: TimeConversion
+	) -> SnapshotsResponse {
+		SnapshotsResponse {
+			tickers: self
+				.tickers
+				.into_iter()
+				.map(|t| t.into_snapshot(market, conversion))
+				.collect(),
+			status:  self.status,
+			uri:     Some(uri)
+		}
+	}
+}
+
+impl WireSnapshotResponse {
+	fn fix(
+		self,
+		market: SnapshotMarket,
+		uri: String,
+		conversion: TimeConversion
+	) -> SnapshotResponse {
+		SnapshotResponse {
+			ticker: self.ticker.into_snapshot(market, conversion),
+			status: self.status,
+			uri:    Some(uri)
+		}
+	}
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SnapshotsParams {
+	pub(crate) params: Query
+}
+
+impl SnapshotsParams {
+	with_param!(include_otc, bool);
+
+	// Only these symbols instead of every ticker in the market
+	pub fn tickers(mut self, tickers: &[&str]) -> Self {
+		self.params.set("tickers", tickers.join(","));
+		self
+	}
+
+	pub fn new() -> Self { Self::default() }
+}
+
+impl AsRef<Query> for SnapshotsParams {
+	fn as_ref(&self) -> &Query { &self.params }
+}
+
+// Per-exchange sizes at one price
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BookPrice {
+	#[serde(rename(deserialize = "p"))]
+	pub price: f64,
+	// Keyed by exchange id
+	#[serde(rename(deserialize = "x"))]
+	pub sizes: HashMap<u8, f64>
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct Book {
+	#[serde(rename(deserialize = "ticker"))]
+	pub symbol:    String,
+	#[serde(default)]
+	pub bids:      Vec<BookPrice>,
+	#[serde(default)]
+	pub asks:      Vec<BookPrice>,
+	// Total size on each side
+	#[serde(default)]
+	pub bid_count: f64,
+	#[serde(default)]
+	pub ask_count: f64,
+	#[serde(default)]
+	pub spread:    f64,
+	pub updated:   Timestamp
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BookResponse {
+	pub data:   Book,
+	// For debugging
+	pub status: String,
+	pub uri:    Option<String>
+}
+
+fn snapshots_uri(
+	api_uri: &str,
+	market: SnapshotMarket,
+	params: Option<&SnapshotsParams>
+) -> String {
+	format!(
+		"{}/v2/snapshot/{}/tickers{}",
+		api_uri,
+		market.path(),
+		make_params(params)
+	)
+}
+
+fn snapshot_uri(api_uri: &str, market: SnapshotMarket, symbol: &str) -> String {
+	format!(
+		"{}/v2/snapshot/{}/tickers/{}",
+		api_uri,
+		market.path(),
+		encode_segment(symbol)
+	)
+}
+
+fn movers_uri(api_uri: &str, market: SnapshotMarket, direction: Direction) -> String {
+	format!("{}/v2/snapshot/{}/{}", api_uri, market.path(), direction)
+}
+
+fn book_uri(api_uri: &str, symbol: &str) -> String {
+	format!(
+		"{}/v2/snapshot/{}/tickers/{}/book",
+		api_uri,
+		SnapshotMarket::Crypto.path(),
+		encode_segment(symbol)
+	)
+}
+
+impl Client {
+	pub fn get_snapshots(
+		&self,
+		market: SnapshotMarket,
+		params: Option<&SnapshotsParams>
+	) -> Result<SnapshotsResponse> {
+		let uri = snapshots_uri(&self.api_uri, market, params);
+
+		let resp = self.get_response::<WireSnapshotsResponse>(&uri)?;
+		Ok(resp.fix(market, uri, self.time_conversion))
+	}
+
+	pub fn get_snapshot(&self, market: SnapshotMarket, symbol: &str) -> Result<SnapshotResponse> {
+		let uri = snapshot_uri(&self.api_uri, market, symbol);
+
+		let resp = self.get_response::<WireSnapshotResponse>(&uri)?;
+		Ok(resp.fix(market, uri, self.time_conversion))
+	}
+
+	// Top 20 gainers or losers since the previous close
+	pub fn get_snapshot_movers(
+		&self,
+		market: SnapshotMarket,
+		direction: Direction
+	) -> Result<SnapshotsResponse> {
+		let uri = movers_uri(&self.api_uri, market, direction);
+
+		let resp = self.get_response::<WireSnapshotsResponse>(&uri)?;
+		Ok(resp.fix(market, uri, self.time_conversion))
+	}
+
+	pub fn get_crypto_book(&self, symbol: &str) -> Result<BookResponse> {
+		let uri = book_uri(&self.api_uri, symbol);
+
+		let mut resp = self.get_response::<BookResponse>(&uri)?;
+		resp.data.updated = resp
+			.data
+			.updated
+			.with_zone(self.time_conversion.zone(symbol));
+		resp.uri = Some(uri);
+
+		Ok(resp)
+	}
+}
+
+#[cfg(feature = "async")]
+impl AsyncClient {
+	pub async fn get_snapshots(
+		&self,
+		market: SnapshotMarket,
+		params: Option<&SnapshotsParams>
+	) -> Result<SnapshotsResponse> {
+		let uri = snapshots_uri(&self.api_uri, market, params);
+
+		let resp = self.get_response::<WireSnapshotsResponse>(&uri).await?;
+		Ok(resp.fix(market, uri, self.time_conversion))
+	}
+
+	pub async fn get_snapshot(
+		&self,
+		market: SnapshotMarket,
+		symbol: &str
+	) -> Result<SnapshotResponse> {
+		let uri = snapshot_uri(&self.api_uri, market, symbol);
+
+		let resp = self.get_response::<WireSnapshotResponse>(&uri).await?;
+		Ok(resp.fix(market, uri, self.time_conversion))
+	}
+
+	// Top 20 gainers or losers since the previous close
+	pub async fn get_snapshot_movers(
+		&self,
+		market: SnapshotMarket,
+		direction: Direction
+	) -> Result<SnapshotsResponse> {
+		let uri = movers_uri(&self.api_uri, market, direction);
+
+		let resp = self.get_response::<WireSnapshotsResponse>(&uri).await?;
+		Ok(resp.fix(market, uri, self.time_conversion))
+	}
+
+	pub async fn get_crypto_book(&self, symbol: &str) -> Result<BookResponse> {
+		let uri = book_uri(&self.api_uri, symbol);
+
+		let mut resp = self.get_response::<BookResponse>(&uri).await?;
+		resp.data.updated = resp
+			.data
+			.updated
+			.with_zone(self.time_conversion.zone(symbol));
+		resp.uri = Some(uri);
+
+		Ok(resp)
+	}
+}
+
+#[cfg(test)]
+mod snapshot {
+	use super::{Direction, SnapshotMarket, SnapshotsParams};
+	use crate::test_server::{StubResponse, StubServer};
+	use chrono_tz::{America::New_York, UTC};
+
+	const AAPL: &str = r#"{"status":"OK","ticker":{"ticker":"AAPL","todaysChange":1.5,
+		"todaysChangePerc":1.2,"updated":1578061800000000000,
+		"day":{"o":74.06,"h":75.15,"l":73.8,"c":74.36,"v":146322800,"vw":74.5},
+		"prevDay":{"o":74.29,"h":75.15,"l":73.8,"c":75.09,"v":135480400,"vw":74.9},
+		"min":{"av":1000,"t":1578061740000,"n":12,"o":74.3,"h":74.4,"l":74.2,"c":74.36,"v":1000,"vw":74.3},
+		"lastTrade":{"c":[14,41],"i":"71675577320245","p":74.36,"s":100,"t":1578061799000000000,"x":4},
+		"lastQuote":{"P":74.37,"S":3,"p":74.35,"s":2,"t":1578061799500000000}}}"#;
+
+	#[test]
+	fn stocks() {
+		let server = StubServer::new(vec![StubResponse::new(200, AAPL)]);
+		let snap = server
+			.client()
+			.get_snapshot(SnapshotMarket::Stocks, "AAPL")
+			.unwrap()
+			.ticker;
+		assert!(server
+			.request(0)
+			.starts_with("GET /v2/snapshot/locale/us/markets/stocks/tickers/AAPL "));
+
+		assert_eq!(snap.day.unwrap().volume, 146322800.0);
+		assert_eq!(snap.prev_day.unwrap().close, 75.09);
+		let min = snap.min.unwrap();
+		assert_eq!(min.ts.to_string(), "2020-01-03 09:29:00.000000000 EST");
+		assert_eq!(min.symbol, "AAPL");
+		assert_eq!(min.num_ticks, 12);
+		let trade = snap.last_trade.unwrap();
+		assert_eq!(trade.time.nanos(), 1578061799000000000);
+		assert_eq!(trade.id, "71675577320245");
+		assert_eq!(trade.conditions, vec![14, 41]);
+		let quote = snap.last_quote.unwrap();
+		assert_eq!(quote.ask_price, 74.37);
+		assert_eq!(quote.bid_lots, Some(2));
+		assert_eq!(quote.exchange, None);
+		assert_eq!(snap.updated.zone(), New_York);
+	}
+
+	#[test]
+	fn movers_and_tickers() {
+		let body = r#"{"status":"OK","tickers":[{"ticker":"X:BTCUSD","updated":1578061800000000000,
+			"day":{"o":1,"h":2,"l":0.5,"c":1.5,"v":10.4},
+			"min":{"t":1578061740000,"n":3,"o":1.4,"h":1.5,"l":1.4,"c":1.5,"v":0.75},
+			"lastTrade":{"c":[1],"i":"a1b2c3d4-e5f6","p":1.5,"s":0.25,"t":1578061799000,"x":1}}]}"#;
+		let forex = r#"{"status":"OK","tickers":[{"ticker":"C:GBPUSD","updated":1578061800000000000,
+			"lastQuote":{"a":1.31065,"b":1.31062,"t":1578061799000,"x":48}}]}"#;
+		let server = StubServer::new(vec![
+			StubResponse::new(200, body),
+			StubResponse::new(200, forex),
+		]);
+		let client = server.client();
+
+		let resp = client
+			.get_snapshot_movers(SnapshotMarket::Crypto, Direction::Gainers)
+			.unwrap();
+		assert!(server
+			.request(0)
+			.starts_with("GET /v2/snapshot/locale/global/markets/crypto/gainers "));
+		let snap = &resp.tickers[0];
+		assert_eq!(snap.day.as_ref().unwrap().volume, 10.4);
+		let min = snap.min.as_ref().unwrap();
+		assert_eq!(min.volume, 0.75);
+		assert_eq!(min.ts.zone(), UTC);
+		let trade = snap.last_trade.as_ref().unwrap();
+		assert_eq!(trade.time.nanos(), 1578061799000000000);
+		assert_eq!(trade.id, "a1b2c3d4-e5f6");
+		assert_eq!(trade.size, 0.25);
+
+		let params = SnapshotsParams::new().tickers(&["EURUSD", "C:GBPUSD"]);
+		let resp = client
+			.get_snapshots(SnapshotMarket::Forex, Some(&params))
+			.unwrap();
+		assert!(server.request(1).starts_with(
+			"GET /v2/snapshot/locale/global/markets/forex/tickers?tickers=EURUSD%2CC%3AGBPUSD "
+		));
+		let quote = resp.tickers[0].last_quote.as_ref().unwrap();
+		assert_eq!(quote.bid_price, 1.31062);
+		assert_eq!((quote.bid_lots, quote.exchange), (None, Some(48)));
+	}
+
+	#[test]
+	fn book() {
+		let body = r#"{"status":"OK","data":{"ticker":"X:BTCUSD",
+			"bids":[{"p":16000,"x":{"1":0.5,"2":1}}],"asks":[{"p":16001,"x":{"1":2}}],
+			"bidCount":1.5,"askCount":2,"spread":1,"updated":1578061800000000000}}"#;
+		let server = StubServer::new(vec![StubResponse::new(200, body)]);
+		let book = server.client().get_crypto_book("X:BTCUSD").unwrap().data;
+		assert!(server
+			.request(0)
+			.starts_with("GET /v2/snapshot/locale/global/markets/crypto/tickers/X:BTCUSD/book "));
+		assert_eq!(book.bids[0].sizes[&2], 1.0);
+		assert_eq!(book.asks[0].price, 16001.0);
+	}
+}
@@ -0,0 +1,51 @@
+pub use crate::crypto::CryptoTrade;
+use crate::time::{from_millis, Timestamp};
+use serde::{Deserialize, Serialize};
+
+// { ev, pair, lp, ls, bp, bs, ap, as, t, x, r }
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CryptoQuote {
+	pub pair:      String,
+	#[serde(rename(deserialize = "x"))]
+	pub exchange:  u8,
+	#[serde(rename(deserialize = "bp"))]
+	pub bid_price: f64,
+	#[serde(rename(deserialize = "bs"))]
+	pub bid_size:  f64,
+	#[serde(rename(deserialize = "ap"))]
+	pub ask_price: f64,
+	#[serde(rename(deserialize = "as"))]
+	pub ask_size:  f64,
+	#[serde(rename(deserialize = "t"), deserialize_with = "from_millis")]
+	pub time:      Timestamp,
+	#[serde(rename(deserialize = "r"), deserialize_with = "from_millis")]
+	pub received:  Timestamp
+}
+
+// Sent as [price, size]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(from = "(f64, f64)")]
+pub struct BookLevel {
+	pub price: f64,
+	pub size:  f64
+}
+
+impl From<(f64, f64)> for BookLevel {
+	fn from((price, size): (f64, f64)) -> Self { Self { price, size } }
+}
+
+// Level 2 book for one exchange: { ev, pair, b, a, t, x, r }
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BookUpdate {
+	pub pair:     String,
+	#[serde(rename(deserialize = "x"))]
+	pub exchange: u8,
+	#[serde(rename(deserialize = "b"), default)]
+	pub bids:     Vec<BookLevel>,
+	#[serde(rename(deserialize = "a"), default)]
+	pub asks:     Vec<BookLevel>,
+	#[serde(rename(deserialize = "t"), deserialize_with = "from_millis")]
+	pub time:     Timestamp,
+	#[serde(rename(deserialize = "r"), deserialize_with = "from_millis")]
+	pub received: Timestamp
+}
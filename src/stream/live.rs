@@ -0,0 +1,409 @@
+// Backfills today's rows over REST and then switches to the socket without losing or repeating
+// any at the seam
+use super::{socket_error, Channel, Cluster, Message, Subscription};
+use crate::{
+	client::{Client, Error, Result},
+	core::{aggs::Timespan, Candle},
+	equities::trades::{Trade, TradesParams, MAX_LIMIT},
+	helpers::Order,
+	time::Timestamp
+};
+use chrono::NaiveDate;
+use chrono_tz::America::New_York;
+use std::{
+	collections::{HashSet, VecDeque},
+	net::{Shutdown, TcpStream},
+	sync::mpsc::{self, Receiver},
+	thread,
+	time::{SystemTime, UNIX_EPOCH}
+};
+
+// What the backfill has covered
+#[derive(Default)]
+pub struct Seen {
+	// Sequence numbers restart each day, so a trade after midnight sorts after the backfill
+	max_sequence: Option<(NaiveDate, u64)>,
+	// Trades without a sequence number
+	ids: HashSet<u64>,
+	last_ts: Option<Timestamp>
+}
+
+pub trait Stitch: Sized {
+	fn from_message(msg: Message) -> Option<Self>;
+
+	fn record(&self, seen: &mut Seen);
+
+	// Whether the backfill already had this row
+	fn is_dup(&self, seen: &Seen) -> bool;
+}
+
+impl Stitch for Trade {
+	fn from_message(msg: Message) -> Option<Self> {
+		match msg {
+			Message::Trade(t) => Some(t),
+			_ => None
+		}
+	}
+
+	fn record(&self, seen: &mut Seen) {
+		match self.sequence_number {
+			Some(seq) => seen.max_sequence = seen.max_sequence.max(Some((trade_date(self), seq))),
+			None => {
+				seen.ids.insert(self.id);
+			}
+		}
+	}
+
+	fn is_dup(&self, seen: &Seen) -> bool {
+		match (self.sequence_number, seen.max_sequence) {
+			(Some(seq), Some(max)) => (trade_date(self), seq) <= max,
+			_ => seen.ids.contains(&self.id)
+		}
+	}
+}
+
+fn trade_date(trade: &Trade) -> NaiveDate {
+	trade.time.with_zone(New_York).to_datetime().date_naive()
+}
+
+fn now_nanos() -> i64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(i64::MAX, |d| d.as_nanos() as i64)
+}
+
+fn today() -> NaiveDate {
+	Timestamp::from_nanos(now_nanos(), New_York)
+		.to_datetime()
+		.date_naive()
+}
+
+// Rows from other days wouldn't line up with the socket, which only has today's
+fn check_today(date: NaiveDate) -> Result<()> {
+	let today = today();
+	match date == today {
+		true => Ok(()),
+		false => Err(Error::NotToday { date, today })
+	}
+}
+
+impl Stitch for Candle {
+	fn from_message(msg: Message) -> Option<Self> {
+		match msg {
+			Message::MinuteAgg(c) => Some(c),
+			_ => None
+		}
+	}
+
+	fn record(&self, seen: &mut Seen) { seen.last_ts = seen.last_ts.max(Some(self.ts)); }
+
+	fn is_dup(&self, seen: &Seen) -> bool { seen.last_ts.is_some_and(|ts| self.ts <= ts) }
+}
+
+type Pages<'a, T> = Box<dyn Iterator<Item = Result<Vec<T>>> + 'a>;
+
+// Yields every backfilled row and then live rows the backfill didn't have. Ends after the first
+// error, including a dropped socket, since rows would be missing. Start a new one to resume.
+// Dropping it closes the socket.
+pub struct LiveFeed<'a, T> {
+	backfill: Option<Pages<'a, T>>,
+	rows:     VecDeque<T>,
+	live:     Receiver<Result<Message>>,
+	seen:     Seen,
+	done:     bool,
+	// Shared with the socket thread's connection
+	socket:   TcpStream
+}
+
+impl<'a, T: Stitch> LiveFeed<'a, T> {
+	// Subscribes before `backfill` makes any requests so the two overlap
+	fn new(
+		client: &Client,
+		subscription: Subscription,
+		backfill: impl FnOnce() -> Pages<'a, T>
+	) -> Result<Self> {
+		let mut stream = client.stream(Cluster::Stocks)?.without_reconnect();
+		stream.subscribe(&[subscription])?;
+		let socket = stream.tcp_stream()?;
+		let (tx, rx) = mpsc::channel();
+		// Exits after passing on an error, including the one from `drop` shutting down the socket
+		thread::spawn(move || {
+			for msg in stream {
+				if tx.send(msg).is_err() {
+					return;
+				}
+			}
+		});
+
+		Ok(Self {
+			backfill: Some(backfill()),
+			rows: VecDeque::new(),
+			live: rx,
+			seen: Seen::default(),
+			done: false,
+			socket
+		})
+	}
+
+	// Whether rows now come from the socket
+	pub fn is_live(&self) -> bool { self.backfill.is_none() }
+
+	fn next_row(&mut self) -> Result<T> {
+		loop {
+			if let Some(row) = self.rows.pop_front() {
+				return Ok(row);
+			}
+			match &mut self.backfill {
+				Some(pages) => match pages.next() {
+					Some(page) => {
+						let page = page?;
+						for row in page.iter() {
+							row.record(&mut self.seen);
+						}
+						self.rows.extend(page);
+					}
+					None => self.backfill = None
+				},
+				None => {
+					// The sender hangs up after passing on an error, so this shouldn't happen
+					let msg = self
+						.live
+						.recv()
+						.map_err(|_| socket_error(tungstenite::Error::ConnectionClosed))??;
+					if let Some(row) = T::from_message(msg) {
+						if !row.is_dup(&self.seen) {
+							return Ok(row);
+						}
+					}
+				}
+			}
+		}
+	}
+}
+
+impl<T> Drop for LiveFeed<'_, T> {
+	// Closes the connection, which wakes the socket thread out of its read so it exits
+	fn drop(&mut self) { let _ = self.socket.shutdown(Shutdown::Both); }
+}
+
+impl<T: Stitch> Iterator for LiveFeed<'_, T> {
+	type Item = Result<T>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+		let res = self.next_row();
+		self.done = res.is_err();
+		Some(res)
+	}
+}
+
+impl Client {
+	// `date`'s trades followed by live ones, deduplicated on `sequence_number` or `id`. `date` must
+	// be today in New York.
+	pub fn live_trades(&self, symbol: &str, date: NaiveDate) -> Result<LiveFeed<'_, Trade>> {
+		check_today(date)?;
+		LiveFeed::new(self, Subscription::new(Channel::Trades, symbol), || {
+			let params = TradesParams::new()
+				.limit(MAX_LIMIT)
+				.order(Order::Asc)
+				.timestamp(date);
+			Box::new(self.paginate_trades(symbol, Some(&params)))
+		})
+	}
+
+	// `date`'s finished minute bars followed by live ones. The bar in progress comes from the
+	// socket once it closes. `date` must be today in New York.
+	pub fn live_minute_aggs(
+		&self,
+		symbol: &str,
+		date: NaiveDate
+	) -> Result<LiveFeed<'static, Candle>> {
+		check_today(date)?;
+		LiveFeed::new(self, Subscription::new(Channel::MinuteAggs, symbol), || {
+			let now = now_nanos();
+			let page = self
+				.get_aggs(symbol, 1, Timespan::Minute, date, date, None)
+				.map(|resp| {
+					resp
+						.results
+						.into_iter()
+						.filter(|c| c.ts.nanos().saturating_add(60_000_000_000) <= now)
+						.collect()
+				});
+			Box::new(std::iter::once(page))
+		})
+	}
+}
+
+#[cfg(test)]
+mod live {
+	use super::today;
+	use crate::{
+		client::{Client, Error},
+		range::RangeCheck,
+		test_server::{handshake, send, StubResponse, StubServer, StubSocket}
+	};
+	use chrono::NaiveDate;
+	use std::{
+		sync::{mpsc, Mutex},
+		time::{Duration, SystemTime, UNIX_EPOCH}
+	};
+
+	// 2020-01-02 09:30 EST in milliseconds
+	const OPEN: i64 = 1577975400000;
+	const DAY: i64 = 86_400_000;
+
+	fn client(http: &StubServer, socket: &StubSocket) -> Client {
+		Client::builder()
+			.key("test")
+			.api_uri(&http.uri)
+			.stream_uri(&socket.uri)
+			.range_check(RangeCheck::Off)
+			.build()
+			.unwrap()
+	}
+
+	#[test]
+	fn trades() {
+		let rest = (1..=3)
+			.map(|seq| {
+				format!(
+					r#"{{"tape":3,"id":"{}","sip_timestamp":{},"price":74.06,"size":100,"exchange":11,"sequence_number":{}}}"#,
+					seq,
+					(OPEN + seq) * 1_000_000,
+					seq
+				)
+			})
+			.collect::<Vec<_>>()
+			.join(",");
+		let http = StubServer::new(vec![StubResponse::new(
+			200,
+			&format!(r#"{{"results":[{}],"status":"OK"}}"#, rest)
+		)]);
+		let socket = StubSocket::new(|_, mut socket| {
+			assert_eq!(handshake(&mut socket), "T.AAPL");
+			// Sequence numbers restart the next day
+			let live = [
+				(2, OPEN + 2),
+				(3, OPEN + 3),
+				(4, OPEN + 4),
+				(5, OPEN + 5),
+				(1, OPEN + DAY)
+			]
+			.iter()
+			.map(|(seq, t)| {
+				format!(
+					r#"{{"ev":"T","sym":"AAPL","x":11,"i":"{}","z":3,"p":74.06,"s":100,"t":{},"q":{}}}"#,
+					seq, t, seq
+				)
+			})
+			.collect::<Vec<_>>()
+			.join(",");
+			send(&mut socket, &format!("[{}]", live));
+			let _ = socket.read();
+		});
+
+		let client = client(&http, &socket);
+		let mut feed = client.live_trades("AAPL", today()).unwrap();
+		let mut seqs = Vec::new();
+		for _ in 0..6 {
+			seqs.push(feed.next().unwrap().unwrap().sequence_number.unwrap());
+		}
+		assert_eq!(seqs, vec![1, 2, 3, 4, 5, 1]);
+		assert!(feed.is_live());
+		let request = http.request(0);
+		assert!(request.contains("order=asc"));
+		assert!(request.contains(&format!("timestamp={}", today())));
+	}
+
+	#[test]
+	fn minute_aggs() {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap()
+			.as_millis() as i64;
+		let current = now - now % 60_000;
+		let bar = |t: i64, close: f64| {
+			format!(
+				r#"{{"t":{},"o":1,"h":2,"l":0.5,"c":{},"v":100,"s":{}}}"#,
+				t, close, t
+			)
+		};
+		// The first two bars are done and the last is in progress
+		let rest = [current - 120_000, current - 60_000, current]
+			.iter()
+			.map(|t| bar(*t, 1.0))
+			.collect::<Vec<_>>()
+			.join(",");
+		let http = StubServer::new(vec![StubResponse::new(
+			200,
+			&format!(
+				r#"{{"ticker":"AAPL","queryCount":3,"resultsCount":3,"adjusted":true,"results":[{}],"request_id":"1"}}"#,
+				rest
+			)
+		)]);
+		let socket = StubSocket::new(move |_, mut socket| {
+			assert_eq!(handshake(&mut socket), "AM.AAPL");
+			let live = [current - 60_000, current]
+				.iter()
+				.map(|t| bar(*t, 2.0).replacen('{', r#"{"ev":"AM","sym":"AAPL","#, 1))
+				.collect::<Vec<_>>()
+				.join(",");
+			send(&mut socket, &format!("[{}]", live));
+			let _ = socket.read();
+		});
+
+		let feed = client(&http, &socket)
+			.live_minute_aggs("AAPL", today())
+			.unwrap();
+		let bars = feed
+			.take(3)
+			.map(|c| {
+				let c = c.unwrap();
+				(c.ts.nanos() / 1_000_000, c.close)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(bars, vec![
+			(current - 120_000, 1.0),
+			(current - 60_000, 1.0),
+			(current, 2.0)
+		]);
+	}
+
+	#[test]
+	fn drop_closes_socket() {
+		let http = StubServer::new(vec![StubResponse::new(
+			200,
+			r#"{"results":[],"status":"OK"}"#
+		)]);
+		let (tx, rx) = mpsc::channel();
+		let tx = Mutex::new(tx);
+		let socket = StubSocket::new(move |_, mut socket| {
+			handshake(&mut socket);
+			// Nothing to send, so the feed's thread sits in a read until it's dropped
+			while socket.read().is_ok() {}
+			tx.lock().unwrap().send(()).unwrap();
+		});
+
+		let client = client(&http, &socket);
+		let feed = client.live_trades("AAPL", today()).unwrap();
+		drop(feed);
+		rx.recv_timeout(Duration::from_secs(5)).unwrap();
+	}
+
+	#[test]
+	fn not_today() {
+		let client = Client::builder().key("test").build().unwrap();
+		let date = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+		assert!(matches!(
+			client.live_trades("AAPL", date),
+			Err(Error::NotToday { .. })
+		));
+		assert!(matches!(
+			client.live_minute_aggs("AAPL", date),
+			Err(Error::NotToday { .. })
+		));
+	}
+}
@@ -0,0 +1,839 @@
+use crate::{
+	client::{Client, Error, Result},
+	core::Candle,
+	decode::snippet,
+	equities::{nbbo::NBBO, trades::Trade},
+	time::{TimeConversion, Timestamp}
+};
+use backoff::{backoff::Backoff, ExponentialBackoff};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+	collections::VecDeque,
+	fmt,
+	io::ErrorKind,
+	net::TcpStream,
+	time::{Duration, Instant}
+};
+use tungstenite::{stream::MaybeTlsStream, Message as Frame, WebSocket};
+
+pub mod crypto;
+pub mod forex;
+pub mod live;
+pub mod stocks;
+
+use chrono_tz::UTC;
+use crypto::{BookUpdate, CryptoQuote, CryptoTrade};
+use forex::ForexQuote;
+use stocks::{WireAgg, WireQuote, WireTrade};
+
+// Polygon serves each asset class from its own path
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cluster {
+	Stocks,
+	Crypto,
+	Forex
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Channel {
+	Trades,
+	Quotes,
+	SecondAggs,
+	MinuteAggs,
+	CryptoTrades,
+	CryptoQuotes,
+	CryptoMinuteAggs,
+	// Level 2 book
+	CryptoBook,
+	ForexQuotes,
+	ForexMinuteAggs
+}
+
+impl Channel {
+	fn prefix(&self) -> &'static str {
+		match self {
+			Channel::Trades => "T",
+			Channel::Quotes => "Q",
+			Channel::SecondAggs => "A",
+			Channel::MinuteAggs => "AM",
+			Channel::CryptoTrades => "XT",
+			Channel::CryptoQuotes => "XQ",
+			Channel::CryptoMinuteAggs => "XA",
+			Channel::CryptoBook => "XL2",
+			Channel::ForexQuotes => "C",
+			Channel::ForexMinuteAggs => "CA"
+		}
+	}
+
+	// The only cluster that serves this channel
+	pub fn cluster(&self) -> Cluster {
+		match self {
+			Channel::Trades | Channel::Quotes | Channel::SecondAggs | Channel::MinuteAggs => {
+				Cluster::Stocks
+			}
+			Channel::CryptoTrades
+			| Channel::CryptoQuotes
+			| Channel::CryptoMinuteAggs
+			| Channel::CryptoBook => Cluster::Crypto,
+			Channel::ForexQuotes | Channel::ForexMinuteAggs => Cluster::Forex
+		}
+	}
+}
+
+// Like T.AAPL, XT.BTC-USD or C.USD/EUR. A symbol of "*" subscribes to every symbol.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Subscription {
+	pub channel: Channel,
+	pub symbol:  String
+}
+
+impl Subscription {
+	pub fn new(channel: Channel, symbol: &str) -> Self {
+		Self {
+			channel,
+			symbol: symbol.to_string()
+		}
+	}
+
+	pub fn all(channel: Channel) -> Self { Self::new(channel, "*") }
+}
+
+impl fmt::Display for Subscription {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}.{}", self.channel.prefix(), self.symbol)
+	}
+}
+
+// {"ev":"status","status":"auth_success","message":"authenticated"}
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Status {
+	pub status:  String,
+	pub message: String
+}
+
+// Sent after reconnecting. Anything between `last_seen` and the next message was missed.
+#[derive(Debug)]
+pub struct Gap {
+	// Newest timestamp received before the disconnect
+	pub last_seen: Option<Timestamp>,
+	// What ended the old connection
+	pub reason:    String,
+	pub downtime:  Duration
+}
+
+#[derive(Debug)]
+pub enum Message {
+	Trade(Trade),
+	Quote(NBBO),
+	SecondAgg(Candle),
+	MinuteAgg(Candle),
+	CryptoTrade(CryptoTrade),
+	CryptoQuote(CryptoQuote),
+	CryptoAgg(Candle),
+	CryptoBook(BookUpdate),
+	ForexQuote(ForexQuote),
+	ForexAgg(Candle),
+	Status(Status),
+	Gap(Gap)
+}
+
+impl Message {
+	pub fn timestamp(&self) -> Option<Timestamp> {
+		match self {
+			Message::Trade(t) => Some(t.time),
+			Message::Quote(q) => Some(q.ts),
+			Message::SecondAgg(c)
+			| Message::MinuteAgg(c)
+			| Message::CryptoAgg(c)
+			| Message::ForexAgg(c) => Some(c.ts),
+			Message::CryptoTrade(t) => Some(t.time),
+			Message::CryptoQuote(q) => Some(q.time),
+			Message::CryptoBook(b) => Some(b.time),
+			Message::ForexQuote(q) => Some(q.time),
+			Message::Status(_) | Message::Gap(_) => None
+		}
+	}
+}
+
+// Each frame is an array of these
+#[derive(Debug, Deserialize)]
+#[serde(tag = "ev")]
+enum Event {
+	#[serde(rename = "T")]
+	Trade(WireTrade),
+	#[serde(rename = "Q")]
+	Quote(WireQuote),
+	#[serde(rename = "A")]
+	SecondAgg(WireAgg),
+	#[serde(rename = "AM")]
+	MinuteAgg(WireAgg),
+	#[serde(rename = "XT")]
+	CryptoTrade(CryptoTrade),
+	#[serde(rename = "XQ")]
+	CryptoQuote(CryptoQuote),
+	#[serde(rename = "XA")]
+	CryptoAgg(WireAgg),
+	#[serde(rename = "XL2")]
+	CryptoBook(BookUpdate),
+	#[serde(rename = "C")]
+	ForexQuote(ForexQuote),
+	#[serde(rename = "CA")]
+	ForexAgg(WireAgg),
+	#[serde(rename = "status")]
+	Status(Status),
+	#[serde(other)]
+	Unknown
+}
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+fn socket_error(e: tungstenite::Error) -> Error { Error::StreamError(Box::new(e)) }
+
+// Like "T.AAPL,Q.AAPL"
+fn join(subscriptions: &[Subscription]) -> String {
+	subscriptions
+		.iter()
+		.map(|s| s.to_string())
+		.collect::<Vec<String>>()
+		.join(",")
+}
+
+fn set_read_timeout(socket: &Socket, timeout: Duration) -> Result<()> {
+	let stream = match socket.get_ref() {
+		MaybeTlsStream::Plain(s) => s,
+		MaybeTlsStream::Rustls(s) => s.get_ref(),
+		_ => return Ok(())
+	};
+	stream
+		.set_read_timeout(Some(timeout))
+		.map_err(Error::IoError)
+}
+
+fn open(uri: &str, heartbeat: Duration) -> Result<Socket> {
+	let (socket, _) = tungstenite::connect(uri).map_err(socket_error)?;
+	set_read_timeout(&socket, heartbeat)?;
+	Ok(socket)
+}
+
+pub struct Stream {
+	socket: Socket,
+	cluster: Cluster,
+	uri: String,
+	key: String,
+	time_conversion: TimeConversion,
+	subscriptions: Vec<Subscription>,
+	pending: VecDeque<Message>,
+	done: bool,
+	// `None` to give up on the first disconnect
+	reconnect: Option<ExponentialBackoff>,
+	heartbeat: Duration,
+	awaiting_pong: bool,
+	last_seen: Option<Timestamp>
+}
+
+impl Stream {
+	fn connect(client: &Client, cluster: Cluster) -> Result<Self> {
+		let uri = format!(
+			"{}/{}",
+			client.stream_uri,
+			format!("{:?}", cluster).to_lowercase()
+		);
+		let heartbeat = Duration::from_secs(30);
+		let mut res = Self {
+			socket: open(&uri, heartbeat)?,
+			cluster,
+			uri,
+			key: client.key.clone(),
+			time_conversion: client.time_conversion,
+			subscriptions: Vec::new(),
+			pending: VecDeque::new(),
+			done: false,
+			reconnect: Some(client.backoff.clone()),
+			heartbeat,
+			awaiting_pong: false,
+			last_seen: None
+		};
+		res.authenticate()?;
+
+		Ok(res)
+	}
+
+	// How long to wait for a frame before pinging, and then for the pong before reconnecting.
+	// Defaults to 30 seconds.
+	pub fn with_heartbeat_timeout(mut self, timeout: Duration) -> Result<Self> {
+		set_read_timeout(&self.socket, timeout)?;
+		self.heartbeat = timeout;
+		Ok(self)
+	}
+
+	// Backoff between reconnect attempts. Defaults to the client's.
+	pub fn with_reconnect(mut self, backoff: ExponentialBackoff) -> Self {
+		self.reconnect = Some(backoff);
+		self
+	}
+
+	// Fail with the disconnect instead
+	pub fn without_reconnect(mut self) -> Self {
+		self.reconnect = None;
+		self
+	}
+
+	fn authenticate(&mut self) -> Result<()> {
+		self.expect_status("connected")?;
+		let key = self.key.clone();
+		self.send(json!({ "action": "auth", "params": key }))?;
+		self.expect_status("auth_success")
+	}
+
+	fn send(&mut self, msg: serde_json::Value) -> Result<()> {
+		self
+			.socket
+			.send(Frame::Text(msg.to_string()))
+			.map_err(socket_error)
+	}
+
+	// Skips other messages, which don't come before auth
+	fn expect_status(&mut self, status: &str) -> Result<()> {
+		loop {
+			for msg in self.read_frame()? {
+				match msg {
+					Message::Status(s) if s.status == status => return Ok(()),
+					Message::Status(s) if s.status == "auth_failed" => {
+						return Err(Error::Unauthorized {
+							uri:     self.uri.clone(),
+							message: s.message
+						});
+					}
+					_ => {}
+				}
+			}
+		}
+	}
+
+	// Pings once the heartbeat timeout passes and gives up if it passes again
+	fn read(&mut self) -> Result<Frame> {
+		loop {
+			match self.socket.read() {
+				Ok(frame) => {
+					self.awaiting_pong = false;
+					return Ok(frame);
+				}
+				Err(tungstenite::Error::Io(e))
+					if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+				{
+					if self.awaiting_pong {
+						return Err(socket_error(tungstenite::Error::Io(e)));
+					}
+					self.awaiting_pong = true;
+					self
+						.socket
+						.send(Frame::Ping(Vec::new()))
+						.map_err(socket_error)?;
+				}
+				Err(e) => return Err(socket_error(e))
+			}
+		}
+	}
+
+	fn read_frame(&mut self) -> Result<Vec<Message>> {
+		let text = match self.read()? {
+			Frame::Text(text) => text,
+			Frame::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+			// tungstenite answers pings itself
+			_ => return Ok(Vec::new())
+		};
+		let events = serde_json::from_str::<Vec<Event>>(&text).map_err(|source| Error::Decode {
+			uri: self.uri.clone(),
+			body_snippet: snippet(&text),
+			source
+		})?;
+		let conversion = self.time_conversion;
+		let mut res = Vec::with_capacity(events.len());
+		for event in events {
+			let msg = match event {
+				Event::Trade(t) => {
+					let zone = conversion.zone(&t.symbol);
+					Message::Trade(t.into_trade(zone))
+				}
+				Event::Quote(q) => {
+					let zone = conversion.zone(&q.symbol);
+					Message::Quote(q.into_nbbo(zone))
+				}
+				Event::SecondAgg(a) => {
+					let zone = conversion.zone(&a.symbol);
+					Message::SecondAgg(a.into_candle(zone))
+				}
+				Event::MinuteAgg(a) => {
+					let zone = conversion.zone(&a.symbol);
+					Message::MinuteAgg(a.into_candle(zone))
+				}
+				// Crypto and forex trade around the clock and stay in UTC
+				Event::CryptoTrade(t) => Message::CryptoTrade(t),
+				Event::CryptoQuote(q) => Message::CryptoQuote(q),
+				Event::CryptoAgg(a) => Message::CryptoAgg(a.into_candle(UTC)),
+				Event::CryptoBook(b) => Message::CryptoBook(b),
+				Event::ForexQuote(q) => Message::ForexQuote(q),
+				Event::ForexAgg(a) => Message::ForexAgg(a.into_candle(UTC)),
+				Event::Status(s) => Message::Status(s),
+				Event::Unknown => continue
+			};
+			if let Some(ts) = msg.timestamp() {
+				self.last_seen = self.last_seen.max(Some(ts));
+			}
+			res.push(msg);
+		}
+
+		Ok(res)
+	}
+
+	// Opens a new socket and replays auth and subscriptions
+	fn reconnect(&mut self, backoff: ExponentialBackoff, cause: Error) -> Result<()> {
+		let mut backoff = backoff;
+		backoff.reset();
+		let start = Instant::now();
+		backoff::retry(backoff, || {
+			self.socket = open(&self.uri, self.heartbeat).map_err(backoff::Error::transient)?;
+			self.awaiting_pong = false;
+			self.authenticate().map_err(|e| match e {
+				Error::Unauthorized { .. } => backoff::Error::permanent(e),
+				e => backoff::Error::transient(e)
+			})?;
+			let subscriptions = self.subscriptions.clone();
+			if !subscriptions.is_empty() {
+				self
+					.send(json!({ "action": "subscribe", "params": join(&subscriptions) }))
+					.map_err(backoff::Error::transient)?;
+			}
+			Ok(())
+		})
+		.map_err(|e| match e {
+			backoff::Error::Transient { err, .. } => err,
+			backoff::Error::Permanent(err) => err
+		})?;
+		self.pending.push_back(Message::Gap(Gap {
+			last_seen: self.last_seen,
+			reason:    cause.to_string(),
+			downtime:  start.elapsed()
+		}));
+
+		Ok(())
+	}
+
+	// Blocks until Polygon sends something
+	pub fn next_message(&mut self) -> Result<Message> {
+		loop {
+			if let Some(msg) = self.pending.pop_front() {
+				return Ok(msg);
+			}
+			match self.read_frame() {
+				Ok(msgs) => self.pending.extend(msgs),
+				Err(e @ Error::StreamError(_)) => match self.reconnect.clone() {
+					Some(backoff) => self.reconnect(backoff, e)?,
+					None => return Err(e)
+				},
+				Err(e) => return Err(e)
+			}
+		}
+	}
+
+	// Fails with `Error::WrongCluster` without subscribing to anything if one of `subscriptions`
+	// is for another cluster
+	pub fn subscribe(&mut self, subscriptions: &[Subscription]) -> Result<()> {
+		if let Some(s) = subscriptions
+			.iter()
+			.find(|s| s.channel.cluster() != self.cluster)
+		{
+			return Err(Error::WrongCluster {
+				cluster:      self.cluster,
+				subscription: s.clone()
+			});
+		}
+		if subscriptions.is_empty() {
+			return Ok(());
+		}
+		self.send(json!({ "action": "subscribe", "params": join(subscriptions) }))?;
+		for s in subscriptions {
+			if !self.subscriptions.contains(s) {
+				self.subscriptions.push(s.clone());
+			}
+		}
+
+		Ok(())
+	}
+
+	pub fn unsubscribe(&mut self, subscriptions: &[Subscription]) -> Result<()> {
+		if subscriptions.is_empty() {
+			return Ok(());
+		}
+		self.send(json!({ "action": "unsubscribe", "params": join(subscriptions) }))?;
+		self.subscriptions.retain(|s| !subscriptions.contains(s));
+
+		Ok(())
+	}
+
+	pub fn subscriptions(&self) -> &[Subscription] { &self.subscriptions }
+
+	// The current connection's TCP socket, so another thread can shut it down to wake a blocked
+	// read. Reconnecting replaces it.
+	pub(crate) fn tcp_stream(&self) -> Result<TcpStream> {
+		let stream = match self.socket.get_ref() {
+			MaybeTlsStream::Plain(s) => s,
+			MaybeTlsStream::Rustls(s) => s.get_ref(),
+			_ => return Err(Error::IoError(ErrorKind::Unsupported.into()))
+		};
+		stream.try_clone().map_err(Error::IoError)
+	}
+
+	pub fn close(mut self) -> Result<()> { self.socket.close(None).map_err(socket_error) }
+}
+
+// Ends after the first error
+impl Iterator for Stream {
+	type Item = Result<Message>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+		let res = self.next_message();
+		self.done = res.is_err();
+		Some(res)
+	}
+}
+
+impl Client {
+	// Connects to `stream_uri` and authenticates
+	pub fn stream(&self, cluster: Cluster) -> Result<Stream> { Stream::connect(self, cluster) }
+}
+
+#[cfg(test)]
+mod stream {
+	use super::{Channel, Cluster, Message, Subscription};
+	use crate::{
+		client::Error,
+		test_server::{handshake, recv, send, StubSocket}
+	};
+	use backoff::ExponentialBackoff;
+	use chrono::Timelike;
+	use std::{thread, time::Duration};
+
+	fn trade(t: i64) -> String {
+		format!(
+			r#"[{{"ev":"T","sym":"AAPL","x":11,"i":"1","z":3,"p":74.06,"s":100,"t":{}}}]"#,
+			t
+		)
+	}
+
+	fn fast_backoff() -> ExponentialBackoff {
+		ExponentialBackoff {
+			initial_interval: Duration::from_millis(10),
+			max_elapsed_time: Some(Duration::from_secs(5)),
+			..ExponentialBackoff::default()
+		}
+	}
+
+	fn trade_time(msg: Message) -> i64 {
+		match msg {
+			Message::Trade(t) => t.time.nanos() / 1_000_000,
+			m => panic!("bad message {:?}", m)
+		}
+	}
+
+	const EVENTS: &str = r#"[
+		{"ev":"T","sym":"AAPL","x":11,"i":"52983525029461","z":3,"p":74.06,"s":100,"c":[14,41],"t":1577975400000,"q":1063},
+		{"ev":"Q","sym":"AAPL","bx":11,"bp":74.05,"bs":3,"ax":12,"ap":74.07,"as":2,"c":0,"t":1577975400001,"q":1064,"z":3},
+		{"ev":"AM","sym":"AAPL","v":4110,"av":9470157,"op":74.06,"vw":74.1,"o":74.06,"c":74.2,"h":74.3,"l":74.0,"a":74.05,"z":68,"s":1577975400000,"e":1577975460000},
+		{"ev":"XX","sym":"AAPL"}
+	]"#;
+
+	#[test]
+	fn decodes_stocks() {
+		let server = StubSocket::new(|_, mut socket| {
+			let params = handshake(&mut socket);
+			assert_eq!(params, "T.AAPL,Q.AAPL,AM.*");
+			send(&mut socket, EVENTS);
+			// Keep the connection open until the client hangs up
+			let _ = socket.read();
+		});
+		let mut stream = server.client().stream(Cluster::Stocks).unwrap();
+		stream
+			.subscribe(&[
+				Subscription::new(Channel::Trades, "AAPL"),
+				Subscription::new(Channel::Quotes, "AAPL"),
+				Subscription::all(Channel::MinuteAggs)
+			])
+			.unwrap();
+		assert_eq!(stream.subscriptions().len(), 3);
+
+		match stream.next_message().unwrap() {
+			Message::Trade(t) => {
+				assert_eq!(t.ticker, "AAPL");
+				assert_eq!(t.id, 52983525029461);
+				assert_eq!(t.time.nanos(), 1577975400000000000);
+				assert_eq!(t.time.to_datetime().hour(), 9);
+				assert_eq!(t.conditions, vec![14, 41]);
+				assert_eq!(t.sequence_number, Some(1063));
+			}
+			m => panic!("bad message {:?}", m)
+		}
+		match stream.next_message().unwrap() {
+			Message::Quote(q) => {
+				assert_eq!(q.symbol, "AAPL");
+				assert_eq!((q.bid_lots, q.ask_lots), (3, 2));
+				assert_eq!(q.ts.nanos(), 1577975400001000000);
+			}
+			m => panic!("bad message {:?}", m)
+		}
+		match stream.next_message().unwrap() {
+			Message::MinuteAgg(c) => {
+				assert_eq!(c.symbol, "AAPL");
+				assert_eq!(c.volume, 4110.0);
+				assert_eq!(c.ts.nanos(), 1577975400000000000);
+				assert_eq!(c.num_ticks, u64::MAX);
+			}
+			m => panic!("bad message {:?}", m)
+		}
+		stream.close().unwrap();
+	}
+
+	#[test]
+	fn auth_failed() {
+		let server = StubSocket::new(|_, mut socket| {
+			send(
+				&mut socket,
+				r#"[{"ev":"status","status":"connected","message":"Connected Successfully"}]"#
+			);
+			recv(&mut socket);
+			send(
+				&mut socket,
+				r#"[{"ev":"status","status":"auth_failed","message":"authentication failed"}]"#
+			);
+		});
+		match server.client().stream(Cluster::Stocks) {
+			Err(Error::Unauthorized { uri, message }) => {
+				assert_eq!(uri, format!("{}/stocks", server.uri));
+				assert_eq!(message, "authentication failed");
+			}
+			_ => panic!("bad error type")
+		}
+	}
+
+	#[test]
+	fn ends_after_error() {
+		let server = StubSocket::new(|_, mut socket| {
+			handshake(&mut socket);
+			send(&mut socket, r#"[{"ev":"T","sym":"AAPL"}]"#);
+		});
+		let mut stream = server.client().stream(Cluster::Stocks).unwrap();
+		stream
+			.subscribe(&[Subscription::all(Channel::Trades)])
+			.unwrap();
+		match stream.next() {
+			Some(Err(Error::Decode { body_snippet, .. })) => assert!(body_snippet.contains("AAPL")),
+			_ => panic!("bad error type")
+		}
+		assert!(stream.next().is_none());
+	}
+
+	#[test]
+	fn reconnects() {
+		let server = StubSocket::new(|i, mut socket| {
+			// Replays the whole subscription set
+			assert_eq!(handshake(&mut socket), "T.AAPL,T.MSFT");
+			send(&mut socket, &trade(1000 + i as i64));
+			if i > 0 {
+				let _ = socket.read();
+			}
+		});
+		let mut stream = server
+			.client()
+			.stream(Cluster::Stocks)
+			.unwrap()
+			.with_reconnect(fast_backoff());
+		stream
+			.subscribe(&[
+				Subscription::new(Channel::Trades, "AAPL"),
+				Subscription::new(Channel::Trades, "MSFT")
+			])
+			.unwrap();
+		assert_eq!(trade_time(stream.next_message().unwrap()), 1000);
+		match stream.next_message().unwrap() {
+			Message::Gap(gap) => assert_eq!(gap.last_seen.unwrap().nanos(), 1_000_000_000),
+			m => panic!("bad message {:?}", m)
+		}
+		assert_eq!(trade_time(stream.next_message().unwrap()), 1001);
+	}
+
+	#[test]
+	fn heartbeat_timeout() {
+		let server = StubSocket::new(|i, mut socket| {
+			handshake(&mut socket);
+			send(&mut socket, &trade(1000 + i as i64));
+			if i == 0 {
+				// Hang without answering pings
+				thread::sleep(Duration::from_secs(2));
+			} else {
+				let _ = socket.read();
+			}
+		});
+		let mut stream = server
+			.client()
+			.stream(Cluster::Stocks)
+			.unwrap()
+			.with_heartbeat_timeout(Duration::from_millis(50))
+			.unwrap()
+			.with_reconnect(fast_backoff());
+		stream
+			.subscribe(&[Subscription::all(Channel::Trades)])
+			.unwrap();
+		assert_eq!(trade_time(stream.next_message().unwrap()), 1000);
+		assert!(matches!(stream.next_message().unwrap(), Message::Gap(_)));
+		assert_eq!(trade_time(stream.next_message().unwrap()), 1001);
+	}
+
+	#[test]
+	fn without_reconnect() {
+		let server = StubSocket::new(|_, mut socket| {
+			handshake(&mut socket);
+		});
+		let mut stream = server
+			.client()
+			.stream(Cluster::Stocks)
+			.unwrap()
+			.without_reconnect();
+		stream
+			.subscribe(&[Subscription::all(Channel::Trades)])
+			.unwrap();
+		assert!(matches!(stream.next(), Some(Err(Error::StreamError(_)))));
+		assert!(stream.next().is_none());
+	}
+
+	#[test]
+	fn decodes_crypto() {
+		let server = StubSocket::new(|_, mut socket| {
+			assert_eq!(
+				handshake(&mut socket),
+				"XT.BTC-USD,XQ.BTC-USD,XL2.BTC-USD,XA.*"
+			);
+			send(
+				&mut socket,
+				r#"[
+					{"ev":"XT","pair":"BTC-USD","p":33021.9,"t":1610462007425,"s":0.01544,"c":[1],"i":"129472998","x":1,"r":1610462007576},
+					{"ev":"XQ","pair":"BTC-USD","lp":0,"ls":0,"bp":33052.79,"bs":0.48,"ap":33073.19,"as":0.601,"t":1610462411115,"x":1,"r":1610462411128},
+					{"ev":"XL2","pair":"BTC-USD","t":1610462411115,"r":1610462411128,"x":12,"b":[[33712.7,0.0035]],"a":[[33718.23,3.5],[33719.1,0.2]]},
+					{"ev":"XA","pair":"BTC-USD","v":951.6112,"vw":33050.1,"z":0,"o":33000.0,"c":33100.0,"h":33200.0,"l":32900.0,"s":1610463240000,"e":1610463300000}
+				]"#
+			);
+			let _ = socket.read();
+		});
+		let mut stream = server.client().stream(Cluster::Crypto).unwrap();
+		stream
+			.subscribe(&[
+				Subscription::new(Channel::CryptoTrades, "BTC-USD"),
+				Subscription::new(Channel::CryptoQuotes, "BTC-USD"),
+				Subscription::new(Channel::CryptoBook, "BTC-USD"),
+				Subscription::all(Channel::CryptoMinuteAggs)
+			])
+			.unwrap();
+
+		match stream.next_message().unwrap() {
+			Message::CryptoTrade(t) => {
+				assert_eq!(t.pair, "BTC-USD");
+				assert_eq!(t.id, "129472998");
+				assert_eq!(t.size, 0.01544);
+				assert_eq!(t.time.nanos(), 1610462007425000000);
+				assert_eq!(t.received.unwrap().nanos(), 1610462007576000000);
+			}
+			m => panic!("bad message {:?}", m)
+		}
+		match stream.next_message().unwrap() {
+			Message::CryptoQuote(q) => assert_eq!((q.bid_size, q.ask_size), (0.48, 0.601)),
+			m => panic!("bad message {:?}", m)
+		}
+		match stream.next_message().unwrap() {
+			Message::CryptoBook(b) => {
+				assert_eq!(b.exchange, 12);
+				assert_eq!(b.bids.len(), 1);
+				assert_eq!((b.asks[1].price, b.asks[1].size), (33719.1, 0.2));
+			}
+			m => panic!("bad message {:?}", m)
+		}
+		match stream.next_message().unwrap() {
+			Message::CryptoAgg(c) => {
+				assert_eq!(c.symbol, "BTC-USD");
+				assert_eq!(c.volume, 951.6112);
+				assert_eq!(c.vwap, 33050.1);
+				// Stays in UTC even though the pair has no ':'
+				assert_eq!(c.ts.to_datetime().hour(), 14);
+			}
+			m => panic!("bad message {:?}", m)
+		}
+	}
+
+	#[test]
+	fn decodes_forex() {
+		let server = StubSocket::new(|_, mut socket| {
+			assert_eq!(handshake(&mut socket), "C.USD/EUR,CA.*");
+			send(
+				&mut socket,
+				r#"[
+					{"ev":"C","p":"USD/EUR","x":44,"a":0.8217,"b":0.8216,"t":1610462411115},
+					{"ev":"CA","pair":"USD/EUR","o":0.8687,"c":0.86889,"h":0.86889,"l":0.8686,"v":20,"s":1539145740000}
+				]"#
+			);
+			let _ = socket.read();
+		});
+		let mut stream = server.client().stream(Cluster::Forex).unwrap();
+		stream
+			.subscribe(&[
+				Subscription::new(Channel::ForexQuotes, "USD/EUR"),
+				Subscription::all(Channel::ForexMinuteAggs)
+			])
+			.unwrap();
+
+		match stream.next_message().unwrap() {
+			Message::ForexQuote(q) => {
+				assert_eq!(q.pair, "USD/EUR");
+				assert_eq!((q.bid, q.ask), (0.8216, 0.8217));
+			}
+			m => panic!("bad message {:?}", m)
+		}
+		match stream.next_message().unwrap() {
+			Message::ForexAgg(c) => {
+				assert_eq!(c.volume, 20.0);
+				assert_eq!(c.ts.nanos(), 1539145740000000000);
+				assert_eq!(c.ts.to_datetime().hour(), 4);
+			}
+			m => panic!("bad message {:?}", m)
+		}
+	}
+
+	#[test]
+	fn wrong_cluster() {
+		let server = StubSocket::new(|_, mut socket| {
+			assert_eq!(handshake(&mut socket), "XT.*");
+			let _ = socket.read();
+		});
+		let mut stream = server.client().stream(Cluster::Crypto).unwrap();
+		match stream.subscribe(&[
+			Subscription::all(Channel::CryptoTrades),
+			Subscription::new(Channel::ForexQuotes, "USD/EUR")
+		]) {
+			Err(Error::WrongCluster {
+				cluster,
+				subscription
+			}) => {
+				assert_eq!(cluster, Cluster::Crypto);
+				assert_eq!(subscription.to_string(), "C.USD/EUR");
+			}
+			_ => panic!("bad error type")
+		}
+		assert!(stream.subscriptions().is_empty());
+		// Nothing was sent, so this is the first subscribe the server sees
+		stream
+			.subscribe(&[Subscription::all(Channel::CryptoTrades)])
+			.unwrap();
+		stream.close().unwrap();
+	}
+}
@@ -0,0 +1,138 @@
+use crate::{
+	core::{default_num_ticks, Candle},
+	equities::{
+		nbbo::NBBO,
+		trades::{f64_to_u32, to_id, Trade}
+	},
+	time::{from_millis, Timestamp}
+};
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+// { ev, sym, x, i, z, p, s, c, t, q, trfi, trft }
+#[derive(Debug, Deserialize)]
+pub(crate) struct WireTrade {
+	#[serde(rename = "sym")]
+	pub(crate) symbol: String,
+	#[serde(rename = "x")]
+	exchange: u8,
+	#[serde(rename = "i", deserialize_with = "to_id", default)]
+	id: u64,
+	#[serde(rename = "z")]
+	tape: u8,
+	#[serde(rename = "p")]
+	price: f64,
+	#[serde(rename = "s", deserialize_with = "f64_to_u32", default)]
+	size: u32,
+	#[serde(rename = "c", default)]
+	conditions: Vec<u8>,
+	#[serde(rename = "t", deserialize_with = "from_millis")]
+	time: Timestamp,
+	#[serde(rename = "q")]
+	sequence_number: Option<u64>,
+	#[serde(rename = "trfi")]
+	trf: Option<u8>,
+	// Milliseconds
+	#[serde(rename = "trft")]
+	time_trf: Option<i64>
+}
+
+impl WireTrade {
+	pub(crate) fn into_trade(self, zone: Tz) -> Trade {
+		Trade {
+			sequence_number: self.sequence_number,
+			tape: self.tape,
+			id: self.id,
+			ticker: self.symbol,
+			time: self.time.with_zone(zone),
+			time_participant: None,
+			time_trf: self.time_trf.map(|t| Timestamp::from_millis(t, zone)),
+			price: self.price,
+			size: self.size,
+			conditions: self.conditions,
+			correction: 0,
+			exchange: self.exchange,
+			trf: self.trf
+		}
+	}
+}
+
+// { ev, sym, bx, bp, bs, ax, ap, as, c, i, t, q, z }
+#[derive(Debug, Deserialize)]
+pub(crate) struct WireQuote {
+	#[serde(rename = "sym")]
+	pub(crate) symbol: String,
+	#[serde(rename = "bx", default)]
+	bid_exchange: u32,
+	#[serde(rename = "bp", default)]
+	bid_price: f32,
+	#[serde(rename = "bs", default)]
+	bid_lots: u32,
+	#[serde(rename = "ax", default)]
+	ask_exchange: u32,
+	#[serde(rename = "ap", default)]
+	ask_price: f32,
+	#[serde(rename = "as", default)]
+	ask_lots: u32,
+	#[serde(rename = "t", deserialize_with = "from_millis")]
+	ts: Timestamp,
+	#[serde(rename = "z")]
+	tape: u32
+}
+
+impl WireQuote {
+	pub(crate) fn into_nbbo(self, zone: Tz) -> NBBO {
+		NBBO {
+			ts: self.ts.with_zone(zone),
+			ts_participant: None,
+			ts_trf: None,
+			symbol: self.symbol,
+			bid_exchange: self.bid_exchange,
+			ask_exchange: self.ask_exchange,
+			bid_lots: self.bid_lots,
+			ask_lots: self.ask_lots,
+			bid_price: self.bid_price,
+			ask_price: self.ask_price,
+			tape: self.tape
+		}
+	}
+}
+
+// { ev, sym, v, av, op, vw, o, c, h, l, a, z, s, e }. Forex sends `pair` instead of `sym`.
+#[derive(Debug, Deserialize)]
+pub(crate) struct WireAgg {
+	#[serde(rename = "sym", alias = "pair")]
+	pub(crate) symbol: String,
+	#[serde(rename = "v")]
+	volume: f64,
+	#[serde(rename = "vw")]
+	vwap: Option<f32>,
+	#[serde(rename = "o")]
+	open: f64,
+	#[serde(rename = "h")]
+	high: f64,
+	#[serde(rename = "l")]
+	low: f64,
+	#[serde(rename = "c")]
+	close: f64,
+	// Bar start
+	#[serde(rename = "s", deserialize_with = "from_millis")]
+	ts: Timestamp
+}
+
+impl WireAgg {
+	pub(crate) fn into_candle(self, zone: Tz) -> Candle {
+		Candle {
+			ts:        self.ts.with_zone(zone),
+			symbol:    self.symbol,
+			open:      self.open,
+			high:      self.high,
+			low:       self.low,
+			close:     self.close,
+			volume:    self.volume,
+			vwap:      self.vwap.unwrap_or(f32::NAN),
+			// Not sent over the socket
+			num_ticks: default_num_ticks()
+		}
+	}
+}
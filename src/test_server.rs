@@ -0,0 +1,188 @@
+// Scripted HTTP/1.1 and WebSocket servers for tests that can't hit Polygon
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::client::Client;
+use std::{
+	io::{BufRead, BufReader, Write},
+	net::TcpListener,
+	sync::{Arc, Mutex},
+	thread,
+	time::Duration
+};
+#[cfg(feature = "stream")]
+use {
+	serde_json::Value,
+	std::net::TcpStream,
+	tungstenite::{Message, WebSocket}
+};
+
+#[derive(Clone)]
+pub struct StubResponse {
+	pub status:  u16,
+	pub headers: Vec<(String, String)>,
+	pub body:    Vec<u8>
+}
+
+impl StubResponse {
+	pub fn new(status: u16, body: &str) -> Self {
+		Self {
+			status,
+			headers: Vec::new(),
+			body: body.as_bytes().to_vec()
+		}
+	}
+
+	pub fn header(mut self, key: &str, val: &str) -> Self {
+		self.headers.push((key.to_string(), val.to_string()));
+		self
+	}
+}
+
+pub struct StubServer {
+	pub uri:      String,
+	// Request line and headers like "GET /v3/trades/AAPL HTTP/1.1\r\nhost: ..."
+	pub requests: Arc<Mutex<Vec<String>>>
+}
+
+impl StubServer {
+	// Serves `responses` in order, repeating the last one once they run out
+	pub fn new(responses: Vec<StubResponse>) -> Self {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let uri = format!("http://{}", listener.local_addr().unwrap());
+		let requests = Arc::new(Mutex::new(Vec::new()));
+		let requests_thread = requests.clone();
+
+		thread::spawn(move || {
+			for (i, stream) in listener.incoming().enumerate() {
+				let mut stream = match stream {
+					Ok(s) => s,
+					Err(_) => return
+				};
+				let mut reader = BufReader::new(stream.try_clone().unwrap());
+				let mut line = String::new();
+				if reader.read_line(&mut line).is_err() {
+					continue;
+				}
+				loop {
+					let mut header = String::new();
+					match reader.read_line(&mut header) {
+						Ok(0) | Err(_) => break,
+						Ok(_) if header == "\r\n" => break,
+						Ok(_) => line.push_str(&header)
+					}
+				}
+				requests_thread.lock().unwrap().push(line);
+
+				let resp = &responses[i.min(responses.len() - 1)];
+				let mut head = format!(
+					"HTTP/1.1 {} STUB\r\nContent-Length: {}\r\nConnection: close\r\n",
+					resp.status,
+					resp.body.len()
+				);
+				for (key, val) in resp.headers.iter() {
+					head.push_str(&format!("{}: {}\r\n", key, val));
+				}
+				head.push_str("\r\n");
+				let _ = stream.write_all(head.as_bytes());
+				let _ = stream.write_all(&resp.body);
+			}
+		});
+
+		Self { uri, requests }
+	}
+
+	pub fn client(&self) -> Client {
+		Client::builder()
+			.key("test")
+			.api_uri(&self.uri)
+			.max_retry_time(Duration::from_secs(10))
+			.build()
+			.unwrap()
+	}
+
+	#[cfg(feature = "async")]
+	pub fn async_client(&self) -> AsyncClient {
+		Client::builder()
+			.key("test")
+			.api_uri(&self.uri)
+			.max_retry_time(Duration::from_secs(10))
+			.build_async()
+			.unwrap()
+	}
+
+	pub fn num_requests(&self) -> usize { self.requests.lock().unwrap().len() }
+
+	pub fn request(&self, i: usize) -> String { self.requests.lock().unwrap()[i].clone() }
+}
+
+#[cfg(feature = "stream")]
+pub struct StubSocket {
+	pub uri: String
+}
+
+#[cfg(feature = "stream")]
+impl StubSocket {
+	// Runs `script` on each connection with its index. Returning from `script` drops the
+	// connection without a close frame.
+	pub fn new(script: impl Fn(usize, WebSocket<TcpStream>) + Send + Sync + 'static) -> Self {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let uri = format!("ws://{}", listener.local_addr().unwrap());
+		let script = Arc::new(script);
+
+		thread::spawn(move || {
+			for (i, stream) in listener.incoming().enumerate() {
+				let socket = match stream.map(tungstenite::accept) {
+					Ok(Ok(s)) => s,
+					_ => continue
+				};
+				let script = script.clone();
+				thread::spawn(move || script(i, socket));
+			}
+		});
+
+		Self { uri }
+	}
+
+	pub fn client(&self) -> Client {
+		Client::builder()
+			.key("test")
+			.stream_uri(&self.uri)
+			.build()
+			.unwrap()
+	}
+}
+
+#[cfg(feature = "stream")]
+pub fn send(socket: &mut WebSocket<TcpStream>, msg: &str) {
+	socket.send(Message::Text(msg.to_string())).unwrap();
+}
+
+// Next text frame as JSON
+#[cfg(feature = "stream")]
+pub fn recv(socket: &mut WebSocket<TcpStream>) -> Value {
+	loop {
+		match socket.read().unwrap() {
+			Message::Text(text) => return serde_json::from_str(&text).unwrap(),
+			_ => continue
+		}
+	}
+}
+
+// Connects and authenticates like Polygon, returning the first subscribe action's params
+#[cfg(feature = "stream")]
+pub fn handshake(socket: &mut WebSocket<TcpStream>) -> String {
+	send(
+		socket,
+		r#"[{"ev":"status","status":"connected","message":"Connected Successfully"}]"#
+	);
+	let auth = recv(socket);
+	assert_eq!(auth["action"], "auth");
+	assert_eq!(auth["params"], "test");
+	send(
+		socket,
+		r#"[{"ev":"status","status":"auth_success","message":"authenticated"}]"#
+	);
+	let sub = recv(socket);
+	assert_eq!(sub["action"], "subscribe");
+	sub["params"].as_str().unwrap().to_string()
+}
@@ -0,0 +1,21 @@
+// A regular trade for tests to adjust with struct update syntax
+use crate::{equities::trades::Trade, time::Timestamp};
+use chrono_tz::America::New_York;
+
+pub fn trade(nanos: i64) -> Trade {
+	Trade {
+		sequence_number: None,
+		tape: 3,
+		id: 0,
+		ticker: "AAPL".to_string(),
+		time: Timestamp::from_nanos(nanos, New_York),
+		time_participant: None,
+		time_trf: None,
+		price: 1.0,
+		size: 100,
+		conditions: Vec::new(),
+		correction: 0,
+		exchange: 4,
+		trf: None
+	}
+}
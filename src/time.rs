@@ -0,0 +1,273 @@
+use chrono::{DateTime, NaiveDate, Offset, TimeZone};
+use chrono_tz::{America::New_York, Tz, UTC};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+	cmp::Ordering,
+	fmt,
+	hash::{Hash, Hasher}
+};
+
+// Which zone timestamps are presented in. Crypto and forex symbols (which contain a ':') trade
+// around the clock and always stay in UTC.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeConversion {
+	// America/New_York so the open is 09:30 all year
+	#[default]
+	Exchange,
+	// UTC as sent by Polygon
+	Utc
+}
+
+impl TimeConversion {
+	pub fn zone(&self, symbol: &str) -> Tz {
+		let is_equity = !symbol.contains(':');
+		match self {
+			TimeConversion::Exchange if is_equity => New_York,
+			_ => UTC
+		}
+	}
+}
+
+// An instant as nanoseconds since the Unix epoch plus the zone to present it in. Comparisons only
+// look at the instant. Serializes as nanoseconds.
+#[derive(Clone, Copy, Debug)]
+pub struct Timestamp {
+	nanos: i64,
+	zone:  Tz
+}
+
+impl Timestamp {
+	pub fn from_nanos(nanos: i64, zone: Tz) -> Self { Self { nanos, zone } }
+
+	pub fn from_millis(millis: i64, zone: Tz) -> Self { Self::from_nanos(millis * 1_000_000, zone) }
+
+	// Nanoseconds since the Unix epoch
+	pub fn nanos(&self) -> i64 { self.nanos }
+
+	pub fn zone(&self) -> Tz { self.zone }
+
+	pub fn with_zone(self, zone: Tz) -> Self { Self { zone, ..self } }
+
+	pub fn to_datetime(&self) -> DateTime<Tz> { self.zone.timestamp_nanos(self.nanos) }
+
+	// Nanoseconds since the epoch of the wall clock in `zone`, following DST. 09:30 EDT and
+	// 09:30 EST map to the same time of day.
+	pub fn wall_nanos(&self) -> i64 {
+		let offset = self.to_datetime().offset().fix().local_minus_utc() as i64;
+		self.nanos + offset * 1_000_000_000
+	}
+}
+
+impl PartialEq for Timestamp {
+	fn eq(&self, other: &Self) -> bool { self.nanos == other.nanos }
+}
+
+impl Eq for Timestamp {}
+
+impl PartialOrd for Timestamp {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for Timestamp {
+	fn cmp(&self, other: &Self) -> Ordering { self.nanos.cmp(&other.nanos) }
+}
+
+impl Hash for Timestamp {
+	fn hash<H: Hasher>(&self, state: &mut H) { self.nanos.hash(state) }
+}
+
+impl fmt::Display for Timestamp {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"{}",
+			self.to_datetime().format("%Y-%m-%d %H:%M:%S%.9f %Z")
+		)
+	}
+}
+
+impl From<Timestamp> for DateTime<Tz> {
+	fn from(ts: Timestamp) -> Self { ts.to_datetime() }
+}
+
+impl Serialize for Timestamp {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_i64(self.nanos)
+	}
+}
+
+// v3 endpoints send UTC nanoseconds
+impl<'de> Deserialize<'de> for Timestamp {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		i64::deserialize(deserializer).map(|nanos| Timestamp::from_nanos(nanos, UTC))
+	}
+}
+
+// v2 aggregates send UTC milliseconds
+pub(crate) fn from_millis<'de, D>(deserializer: D) -> Result<Timestamp, D::Error>
+where
+	D: Deserializer<'de>
+{
+	i64::deserialize(deserializer).map(|millis| Timestamp::from_millis(millis, UTC))
+}
+
+// Like `from_millis` for fields that aren't always sent
+pub(crate) fn from_millis_opt<'de, D>(deserializer: D) -> Result<Option<Timestamp>, D::Error>
+where
+	D: Deserializer<'de>
+{
+	Option::<i64>::deserialize(deserializer)
+		.map(|millis| millis.map(|millis| Timestamp::from_millis(millis, UTC)))
+}
+
+// Dates and times for query parameters. Polygon takes YYYY-MM-DD dates everywhere, millisecond
+// timestamps for aggregates and nanosecond timestamps for v3 endpoints. Integers are nanoseconds.
+pub trait QueryTime {
+	fn nanos_param(&self) -> String;
+
+	fn millis_param(&self) -> String;
+}
+
+impl QueryTime for NaiveDate {
+	fn nanos_param(&self) -> String { self.format("%Y-%m-%d").to_string() }
+
+	fn millis_param(&self) -> String { self.nanos_param() }
+}
+
+impl QueryTime for i64 {
+	fn nanos_param(&self) -> String { self.to_string() }
+
+	fn millis_param(&self) -> String { self.div_euclid(1_000_000).to_string() }
+}
+
+impl QueryTime for Timestamp {
+	fn nanos_param(&self) -> String { self.nanos.nanos_param() }
+
+	fn millis_param(&self) -> String { self.nanos.millis_param() }
+}
+
+// Times outside of 1677-2262 don't fit in nanoseconds and are clamped
+impl<Z: TimeZone> QueryTime for DateTime<Z> {
+	fn nanos_param(&self) -> String {
+		self
+			.timestamp_nanos_opt()
+			.unwrap_or(if self.timestamp() < 0 {
+				i64::MIN
+			} else {
+				i64::MAX
+			})
+			.nanos_param()
+	}
+
+	fn millis_param(&self) -> String { self.timestamp_millis().to_string() }
+}
+
+impl<T: QueryTime + ?Sized> QueryTime for &T {
+	fn nanos_param(&self) -> String { (**self).nanos_param() }
+
+	fn millis_param(&self) -> String { (**self).millis_param() }
+}
+
+#[cfg(test)]
+mod time {
+	use super::{QueryTime, TimeConversion, Timestamp};
+	use chrono::{NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+	use chrono_tz::{America::New_York, UTC};
+
+	fn ms(date: &str, time: &str) -> i64 {
+		NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S")
+			.unwrap()
+			.and_utc()
+			.timestamp_millis()
+	}
+
+	// Wall clock time in New York of a UTC time
+	fn exchange_ms(date: &str, time: &str) -> i64 {
+		Timestamp::from_millis(ms(date, time), New_York).wall_nanos() / 1_000_000
+	}
+
+	#[test]
+	fn open_all_year() {
+		// 09:30 EST is 14:30 UTC and 09:30 EDT is 13:30 UTC
+		assert_eq!(
+			exchange_ms("2020-01-02", "14:30:00"),
+			ms("2020-01-02", "09:30:00")
+		);
+		assert_eq!(
+			exchange_ms("2020-07-02", "13:30:00"),
+			ms("2020-07-02", "09:30:00")
+		);
+	}
+
+	#[test]
+	fn march_transition() {
+		// Clocks jump from 02:00 EST to 03:00 EDT at 07:00 UTC on 2021-03-14
+		assert_eq!(
+			exchange_ms("2021-03-14", "06:59:00"),
+			ms("2021-03-14", "01:59:00")
+		);
+		assert_eq!(
+			exchange_ms("2021-03-14", "07:00:00"),
+			ms("2021-03-14", "03:00:00")
+		);
+		assert_eq!(
+			exchange_ms("2021-03-15", "13:30:00"),
+			ms("2021-03-15", "09:30:00")
+		);
+	}
+
+	#[test]
+	fn november_transition() {
+		// Clocks fall back from 02:00 EDT to 01:00 EST at 06:00 UTC on 2021-11-07
+		assert_eq!(
+			exchange_ms("2021-11-07", "05:59:00"),
+			ms("2021-11-07", "01:59:00")
+		);
+		assert_eq!(
+			exchange_ms("2021-11-07", "06:00:00"),
+			ms("2021-11-07", "01:00:00")
+		);
+		assert_eq!(
+			exchange_ms("2021-11-08", "14:30:00"),
+			ms("2021-11-08", "09:30:00")
+		);
+	}
+
+	#[test]
+	fn zones() {
+		assert_eq!(TimeConversion::Exchange.zone("AAPL"), New_York);
+		assert_eq!(TimeConversion::Exchange.zone("X:BTCUSD"), UTC);
+		assert_eq!(TimeConversion::Utc.zone("AAPL"), UTC);
+
+		let utc = Timestamp::from_millis(ms("2021-06-01", "13:30:00"), UTC);
+		let exchange = utc.with_zone(New_York);
+		assert_eq!(utc, exchange);
+		assert_eq!(utc.to_datetime().hour(), 13);
+		assert_eq!(exchange.to_datetime().hour(), 9);
+		assert_eq!(exchange.to_string(), "2021-06-01 09:30:00.000000000 EDT");
+	}
+
+	#[test]
+	fn serde() {
+		let ts = serde_json::from_str::<Timestamp>("1577975400000000000").unwrap();
+		assert_eq!(ts.nanos(), 1577975400000000000);
+		assert_eq!(
+			serde_json::to_string(&ts.with_zone(New_York)).unwrap(),
+			"1577975400000000000"
+		);
+	}
+
+	#[test]
+	fn query_params() {
+		let date = NaiveDate::from_ymd_opt(2020, 1, 2).unwrap();
+		assert_eq!(date.nanos_param(), "2020-01-02");
+		assert_eq!(date.millis_param(), "2020-01-02");
+		let time = Utc.with_ymd_and_hms(2020, 1, 2, 14, 30, 0).unwrap();
+		assert_eq!(time.nanos_param(), "1577975400000000000");
+		assert_eq!(time.millis_param(), "1577975400000");
+		assert_eq!(1577975400000000001i64.nanos_param(), "1577975400000000001");
+		assert_eq!(1577975400000000001i64.millis_param(), "1577975400000");
+		let ts = Timestamp::from_nanos(1577975400000000001, New_York);
+		assert_eq!(ts.to_datetime().nanos_param(), ts.nanos_param());
+	}
+}